@@ -1397,6 +1397,32 @@ mod import_exports {
                       BBBBBBB
                           in 'bbbbbbbbbbbbbbb';
                 ",
+                uses_wrap = {
+                    "
+                        // uses_layout=wrap
+                        program A;
+                        uses AAAA.AAAA, BBB.BBB, CCCCCCC, DD, EEEEE in 'e';
+                        uses A, B;
+                        uses
+                          AAAAAAAAAAAAAAAAAAAAAAAAAAAAAA.AAAAAAAAAA,
+                          B, // b
+                          C;
+                    ",
+                    "
+                        // uses_layout=wrap
+                        program A;
+                        uses
+                          AAAA.AAAA, BBB.BBB, CCCCCCC,
+                          DD, EEEEE in 'e';
+                        uses
+                          A, B;
+                        uses
+                          AAAAAAAAAAAAAAAAAAAAAAAAAAAAAA
+                              .AAAAAAAAAA,
+                          B, // b
+                          C;
+                    ",
+                },
             );
         }
     }
@@ -3071,6 +3097,55 @@ mod routines {
                         BBB: BBB
                     );
                 ",
+                bin_pack = {
+                    "
+                        // parameter_layout=bin_pack
+                        interface
+                        procedure A(B: C; D: E);
+                        procedure Apples(B: C; D: E; F: G; H: I);
+                        procedure Apples(Bananas: C; D, E: F);
+                        procedure Apples(B: C; D: procedure(E: F; G: H));
+                    ",
+                    "
+                        // parameter_layout=bin_pack
+                        interface
+                        procedure A(B: C; D: E);
+                        procedure Apples(
+                            B: C; D: E; F: G; H: I
+                        );
+                        procedure Apples(
+                            Bananas: C; D, E: F
+                        );
+                        procedure Apples(
+                            B: C;
+                            D: procedure(E: F; G: H)
+                        );
+                    ",
+                },
+                one_per_line = {
+                    "
+                        // parameter_layout=one_per_line
+                        interface
+                        procedure A(B: C; D: E);
+                        procedure A(B, C: D);
+                        procedure A<B; C>(D: E);
+                        procedure Apples(B: C; D: procedure(E: F; G: H));
+                    ",
+                    "
+                        // parameter_layout=one_per_line
+                        interface
+                        procedure A(
+                            B: C;
+                            D: E
+                        );
+                        procedure A(B, C: D);
+                        procedure A<B; C>(D: E);
+                        procedure Apples(
+                            B: C;
+                            D: procedure(E: F; G: H)
+                        );
+                    ",
+                },
             );
         }
     }
@@ -3326,6 +3401,67 @@ mod control_flows {
                       Bar;
                     end
                 ",
+                compound_cuddled = {
+                    "
+                        // begin_style=cuddle_else
+                        if A then begin
+                        end
+                        else if A and B then begin
+                          Bar;
+                        end
+                        else if AAAAAAAA and BBBBBB then begin
+                          Bar;
+                        end
+                        else
+                          Bar;
+                    ",
+                    "
+                        // begin_style=cuddle_else
+                        if A then begin
+                        end else if A and B then begin
+                          Bar;
+                        end else if AAAAAAAA
+                            and BBBBBB then
+                        begin
+                          Bar;
+                        end else
+                          Bar;
+                    ",
+                },
+                single_line_statements = {
+                    "
+                        // allow_single_line_statements
+                        if A then
+                          Exit;
+                        if A then
+                          Exit
+                        else
+                          Halt;
+                        if A then
+                          if B then
+                            Exit;
+                        if A then begin
+                          Exit;
+                        end;
+                        if AAAAAAAAA then
+                          Foo(BBBBBBBBBB);
+                    ",
+                    "
+                        // allow_single_line_statements
+                        if A then Exit;
+                        if A then
+                          Exit
+                        else
+                          Halt;
+                        if A then
+                          if B then Exit;
+                        if A then begin
+                          Exit;
+                        end;
+                        if AAAAAAAAA then
+                          Foo(BBBBBBBBBB);
+                    ",
+                },
             );
         }
     }
@@ -3388,6 +3524,21 @@ mod control_flows {
                     begin
                     end;
                 ",
+                single_line_statements = {
+                    "
+                        // allow_single_line_statements
+                        while A < B do
+                          Inc(A);
+                        while AAAAAAAAA < BBBBBBBB do
+                          Inc(A);
+                    ",
+                    "
+                        // allow_single_line_statements
+                        while A < B do Inc(A);
+                        while AAAAAAAAA < BBBBBBBB do
+                          Inc(A);
+                    ",
+                },
             );
         }
     }
@@ -4056,6 +4207,7 @@ mod statements {
     pub fn generate(root_dir: &Path) {
         identifier_chaining::generate(root_dir);
         named_args::generate(root_dir);
+        argument_layout::generate(root_dir);
         inline_declarations::generate(root_dir);
         goto::generate(root_dir);
         raise::generate(root_dir);
@@ -4240,6 +4392,63 @@ mod statements {
         }
     }
 
+    mod argument_layout {
+        use super::*;
+
+        pub fn generate(root_dir: &Path) {
+            generate_test_cases!(
+                root_dir,
+                bin_pack = {
+                    "
+                        // argument_layout=bin_pack
+                        A(B, C);
+                        AAAAAA(BBBB, CCCC, DDDD, EEEE, FFFF, GGGG);
+                        AAAAAA(BBBB, [CCCC, DDDD], EEEE, FFFF);
+                        A := B + CCCCCCCCC(DDDD, EEEE, FFFF, GGGG);
+                    ",
+                    "
+                        // argument_layout=bin_pack
+                        A(B, C);
+                        AAAAAA(
+                            BBBB, CCCC, DDDD, EEEE,
+                            FFFF, GGGG
+                        );
+                        AAAAAA(
+                            BBBB, [CCCC, DDDD], EEEE,
+                            FFFF
+                        );
+                        A :=
+                            B
+                                + CCCCCCCCC(
+                                    DDDD, EEEE, FFFF,
+                                    GGGG);
+                    ",
+                },
+                one_per_line = {
+                    "
+                        // argument_layout=one_per_line
+                        A(B, C);
+                        AAAAAA(BBBB, CCCC, DDDD, EEEE, FFFF, GGGG);
+                        AAAAAAAAAAAAAAAAAA(BBBBBBBBBBBBBBB);
+                    ",
+                    "
+                        // argument_layout=one_per_line
+                        A(B, C);
+                        AAAAAA(
+                            BBBB,
+                            CCCC,
+                            DDDD,
+                            EEEE,
+                            FFFF,
+                            GGGG);
+                        AAAAAAAAAAAAAAAAAA(
+                            BBBBBBBBBBBBBBB);
+                    ",
+                },
+            );
+        }
+    }
+
     mod inline_declarations {
         use super::*;
 
@@ -4500,6 +4709,7 @@ mod expressions {
         set::generate(root_dir);
         strings::generate(root_dir);
         ternary::generate(root_dir);
+        operator_position::generate(root_dir);
     }
 
     mod boolean {
@@ -5322,6 +5532,100 @@ mod expressions {
             );
         }
     }
+
+    mod operator_position {
+        use super::*;
+
+        pub fn generate(root_dir: &Path) {
+            generate_test_cases!(
+                root_dir,
+                after = {
+                    "
+                        // operator_position=after
+                        A := Bbbbbbbbbb + Cccccccccc;
+                        A := Bbbbbbbbbbbbbbbb + Cccccccccccc * Ddddddddddddd - Eeeeeeeeeeee;
+                        if Aaaaaaaaaaaa and Bbbbbbbbbbbb or not Cccccccccc then
+                          A;
+                        A := Bbbbbbbbbbbbb is not Ccccccccc;
+                        A := Bbbbbbbbbbbbbbb.Cccccccccccccc.Dddddddd;
+                    ",
+                    "
+                        // operator_position=after
+                        A := Bbbbbbbbbb + Cccccccccc;
+                        A :=
+                            Bbbbbbbbbbbbbbbb +
+                                Cccccccccccc *
+                                    Ddddddddddddd -
+                                Eeeeeeeeeeee;
+                        if Aaaaaaaaaaaa and
+                                Bbbbbbbbbbbb or
+                            not Cccccccccc then
+                          A;
+                        A :=
+                            Bbbbbbbbbbbbb is not
+                                Ccccccccc;
+                        A :=
+                            Bbbbbbbbbbbbbbb
+                                .Cccccccccccccc
+                                .Dddddddd;
+                    ",
+                },
+                before = {
+                    "
+                        // operator_position=before
+                        A := Bbbbbbbbbb + Cccccccccc;
+                        A := Bbbbbbbbbbbbbbbb + Cccccccccccc * Ddddddddddddd - Eeeeeeeeeeee;
+                        Aaaaaaaaaaaaaaaaa := Bbbbbbbbbbbbbbbbbbbbbbbb;
+                        for Iiiiiiiiiiiiiiiii := Bbbbbbbbbbbbbb to Ccc do
+                          A;
+                    ",
+                    "
+                        // operator_position=before
+                        A := Bbbbbbbbbb + Cccccccccc;
+                        A
+                            := Bbbbbbbbbbbbbbbb
+                                + Cccccccccccc
+                                    * Ddddddddddddd
+                                - Eeeeeeeeeeee;
+                        Aaaaaaaaaaaaaaaaa
+                            := Bbbbbbbbbbbbbbbbbbbbbbbb;
+                        for Iiiiiiiiiiiiiiiii :=
+                                Bbbbbbbbbbbbbb
+                            to Ccc do
+                          A;
+                    ",
+                },
+                comment_before_operator_after = {
+                    "
+                        // operator_position=after
+                        A := Bbbbbbbbbb // comment
+                          + Cccccccccc + Dddddddddd;
+                    ",
+                    "
+                        // operator_position=after
+                        A :=
+                            Bbbbbbbbbb // comment
+                                + Cccccccccc +
+                                Dddddddddd;
+                    ",
+                },
+                comment_before_operator_before = {
+                    "
+                        // operator_position=before
+                        A := Bbbbbbbbbb // comment
+                          + Cccccccccc + Dddddddddd;
+                    ",
+                    "
+                        // operator_position=before
+                        A
+                            := Bbbbbbbbbb // comment
+                                + Cccccccccc
+                                + Dddddddddd;
+                    ",
+                },
+            );
+        }
+    }
 }
 
 mod attributes {
@@ -5412,6 +5716,36 @@ mod line_length_violations {
                             + CCCCCCCCCC // -------
                             + DDDDDDDDDD; // ------
             ",
+            nowrap = "
+                    // pasfmt nowrap
+                    AAAAAAAAA := BBBBBBBBBB + CCCCCCCCCC;
+                    AAAAAAAAA :=
+                        BBBBBBBBBB + CCCCCCCCCC;
+            ",
+            nowrap_joins_existing_breaks = {
+                "
+                    // pasfmt nowrap
+                    AAAAAAAAA :=
+                        BBBBBBBBBB
+                        + CCCCCCCCCC;
+                ",
+                "
+                    // pasfmt nowrap
+                    AAAAAAAAA :=
+                        BBBBBBBBBB + CCCCCCCCCC;
+                ",
+            },
+            nowrap_child_line = "
+                    if A then
+                      // pasfmt nowrap
+                      Foo(AAAAAAAA, BBBBBBBB, CCCCCCCC)
+                    else
+                      Foo(
+                          AAAAAAAA,
+                          BBBBBBBB,
+                          CCCCCCCC
+                      );
+            ",
         );
     }
 }
@@ -42,6 +42,49 @@ fn get_specified_line_length(input: &str) -> Option<u32> {
     )
 }
 
+fn is_uses_layout_wrap(input: &str) -> bool {
+    input.contains("// uses_layout=wrap")
+}
+
+fn get_specified_parameter_layout(input: &str) -> Option<ParameterLayout> {
+    let layout = input.split("// parameter_layout=").nth(1)?;
+    Some(match layout.split_whitespace().next() {
+        Some("bin_pack") => ParameterLayout::BinPack,
+        Some("one_per_line") => ParameterLayout::OnePerLine,
+        Some("one_per_line_if_wrapped") => ParameterLayout::OnePerLineIfWrapped,
+        _ => panic!("`parameter_layout=` should precede a valid layout"),
+    })
+}
+
+fn get_specified_argument_layout(input: &str) -> Option<ArgumentLayout> {
+    let layout = input.split("// argument_layout=").nth(1)?;
+    Some(match layout.split_whitespace().next() {
+        Some("bin_pack") => ArgumentLayout::BinPack,
+        Some("one_per_line") => ArgumentLayout::OnePerLine,
+        Some("one_per_line_break_close") => ArgumentLayout::OnePerLineBreakClose,
+        _ => panic!("`argument_layout=` should precede a valid layout"),
+    })
+}
+
+fn get_specified_operator_positions(input: &str) -> (OperatorPosition, OperatorPosition) {
+    let Some(position) = input.split("// operator_position=").nth(1) else {
+        return (OperatorPosition::Before, OperatorPosition::After);
+    };
+    match position.split_whitespace().next() {
+        Some("before") => (OperatorPosition::Before, OperatorPosition::Before),
+        Some("after") => (OperatorPosition::After, OperatorPosition::After),
+        _ => panic!("`operator_position=` should precede a valid position"),
+    }
+}
+
+fn is_begin_style_cuddle_else(input: &str) -> bool {
+    input.contains("// begin_style=cuddle_else")
+}
+
+fn is_single_line_statements_allowed(input: &str) -> bool {
+    input.contains("// allow_single_line_statements")
+}
+
 fn run_test(input: &str) -> datatest_stable::Result<()> {
     *SET_LOGGER;
 
@@ -50,6 +93,8 @@ fn run_test(input: &str) -> datatest_stable::Result<()> {
     const DEFAULT_MAX_LINE_LENGTH: u32 = 30;
     let max_line_length = get_specified_line_length(input).unwrap_or(DEFAULT_MAX_LINE_LENGTH);
 
+    let (operator_position, assign_position) = get_specified_operator_positions(input);
+
     let reconstruction_settings = ReconstructionSettings::new(LineEnding::Lf, TabKind::Soft, 2, 4);
     let formatter = Formatter::builder()
         .lexer(DelphiLexer {})
@@ -61,9 +106,18 @@ fn run_test(input: &str) -> datatest_stable::Result<()> {
         .file_formatter(OptimisingLineFormatter::new(
             OptimisingLineFormatterSettings {
                 max_line_length,
+                max_blank_lines: 1,
                 iteration_max: 20_000,
+                iteration_limit_fallback: IterationLimitFallback::default(),
                 break_before_begin: false,
+                cuddle_else: is_begin_style_cuddle_else(input),
+                allow_single_line_statements: is_single_line_statements_allowed(input),
                 format_multiline_strings: true,
+                one_import_per_line: !is_uses_layout_wrap(input),
+                parameter_layout: get_specified_parameter_layout(input).unwrap_or_default(),
+                argument_layout: get_specified_argument_layout(input).unwrap_or_default(),
+                operator_position,
+                assign_position,
             },
             reconstruction_settings.clone(),
         ))
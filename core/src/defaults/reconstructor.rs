@@ -294,6 +294,18 @@ impl CursorTracker for CursorTrackerImpl<'_> {
         }
     }
 
+    fn notify_tokens_moved(&mut self, origins: &[usize]) {
+        let mut new_indices = vec![0; origins.len()];
+        for (index, &origin) in origins.iter().enumerate() {
+            new_indices[origin] = index;
+        }
+        for cursor in &mut self.cursors {
+            if let Some(&new_index) = new_indices.get(cursor.tok_idx) {
+                cursor.tok_idx = new_index;
+            }
+        }
+    }
+
     fn relocate_cursors(&mut self, formatted_tokens: &FormattedTokens) {
         for cursor in &mut self.cursors {
             let token = match formatted_tokens.get_token(cursor.tok_idx) {
@@ -631,6 +643,7 @@ mod tests {
             let recon = ReconstructionSettings::new(LineEnding::Lf, TabKind::Soft, 2, 4);
             Formatter::builder()
                 .lexer(DelphiLexer {})
+                .raw_token_consolidator(SortUses::default())
                 .parser(DelphiLogicalLineParser {})
                 .token_ignorer(IgnoreUnknownTokens)
                 .token_remover(DeleteInlineBlockComments)
@@ -640,8 +653,7 @@ mod tests {
                     OptimisingLineFormatterSettings {
                         max_line_length: 30,
                         iteration_max: 1000,
-                        break_before_begin: false,
-                        format_multiline_strings: true,
+                        ..Default::default()
                     },
                     recon.clone(),
                 ))
@@ -735,6 +747,24 @@ const
             );
         }
 
+        #[test]
+        fn through_sorted_uses() {
+            // The cursors move with their units, so they are no longer in order.
+            let (input, mut cursors) = parse_cursors("uses C|c, A|a, |B;\n|");
+            let output = formatter().format(&input, FileOptions::new().with_cursors(&mut cursors));
+
+            pretty_assertions::assert_eq!(output, "uses\n  Aa,\n  B,\n  Cc;\n");
+            pretty_assertions::assert_eq!(
+                cursors,
+                [
+                    Cursor(output.find("Cc").unwrap() as u32 + 1),
+                    Cursor(output.find("Aa").unwrap() as u32 + 1),
+                    Cursor(output.find("B").unwrap() as u32),
+                    Cursor(output.len() as u32),
+                ]
+            );
+        }
+
         #[test]
         fn around_deleted_tokens() {
             assert_cursor("a | |{|} b  {}   {}|", "a |||b|");
@@ -1,4 +1,5 @@
 use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
 use fxhash::FxHashSet;
 
@@ -6,8 +7,8 @@ use crate::lang::*;
 use crate::traits::*;
 
 enum PostParseConsolidatorKind {
-    Line(Box<dyn LogicalLinesConsolidator + Sync>),
-    Token(Box<dyn TokenConsolidator + Sync>),
+    Line(Box<dyn LogicalLinesConsolidator + Send + Sync>),
+    Token(Box<dyn TokenConsolidator + Send + Sync>),
 }
 impl LogicalLinesConsolidator for PostParseConsolidatorKind {
     fn consolidate(&self, (tokens, lines): (&mut [Token], &mut [LogicalLine])) {
@@ -47,33 +48,85 @@ impl TokenMarker {
 }
 
 #[derive(Default)]
-pub struct FileOptions<'cursor> {
-    cursors: &'cursor mut [Cursor],
+pub struct FileOptions<'a> {
+    cursors: &'a mut [Cursor],
+    diagnostics: Option<&'a mut Vec<Diagnostic>>,
+    stage_timings: Option<&'a mut Vec<StageTiming>>,
 }
 
-impl<'cursor> FileOptions<'cursor> {
+impl<'a> FileOptions<'a> {
     pub fn new() -> Self {
         Self::default()
     }
 
-    pub fn with_cursors(mut self, cursors: &'cursor mut [Cursor]) -> Self {
+    pub fn with_cursors(mut self, cursors: &'a mut [Cursor]) -> Self {
         self.cursors = cursors;
         self
     }
+
+    /// Collects the [`Diagnostic`]s reported while formatting the file.
+    pub fn with_diagnostics(mut self, diagnostics: &'a mut Vec<Diagnostic>) -> Self {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    /// Records the wall-clock time spent in each stage of formatting the file.
+    pub fn with_stage_timings(mut self, stage_timings: &'a mut Vec<StageTiming>) -> Self {
+        self.stage_timings = Some(stage_timings);
+        self
+    }
+}
+
+/// The wall-clock time spent in a single stage of formatting a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageTiming {
+    /// The name of the stage, e.g., `parse`, or the name of a formatter type.
+    pub stage: &'static str,
+    pub start: Instant,
+    pub duration: Duration,
+}
+
+/// Times the stages of formatting, only when requested, so that no clock is
+/// read otherwise.
+struct StageTimer<'a> {
+    stage_timings: Option<&'a mut Vec<StageTiming>>,
+}
+impl StageTimer<'_> {
+    fn time<R>(&mut self, stage: &'static str, operation: impl FnOnce() -> R) -> R {
+        let Some(stage_timings) = &mut self.stage_timings else {
+            return operation();
+        };
+        let start = Instant::now();
+        let result = operation();
+        stage_timings.push(StageTiming {
+            stage,
+            start,
+            duration: start.elapsed(),
+        });
+        result
+    }
+}
+
+/// The name of a type without its path or generic arguments, e.g.,
+/// `TokenSpacing`.
+fn short_type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Cursor(pub u32);
 
 pub struct Formatter {
-    lexer: Box<dyn Lexer + Sync>,
-    token_consolidators: Vec<Box<dyn RawTokenConsolidator + Sync>>,
-    logical_line_parser: Box<dyn LogicalLineParser + Sync>,
+    lexer: Box<dyn Lexer + Send + Sync>,
+    token_consolidators: Vec<Box<dyn RawTokenConsolidator + Send + Sync>>,
+    logical_line_parser: Box<dyn LogicalLineParser + Send + Sync>,
     post_parse_consolidators: Vec<PostParseConsolidatorKind>,
-    token_removers: Vec<Box<dyn TokenRemover + Sync>>,
-    token_ignorers: Vec<Box<dyn TokenIgnorer + Sync>>,
-    logical_line_formatters: Vec<FormatterKind>,
-    reconstructor: Box<dyn LogicalLinesReconstructor + Sync>,
+    token_removers: Vec<Box<dyn TokenRemover + Send + Sync>>,
+    token_ignorers: Vec<Box<dyn TokenIgnorer + Send + Sync>>,
+    logical_line_formatters: Vec<(&'static str, FormatterKind)>,
+    reconstructor: Box<dyn LogicalLinesReconstructor + Send + Sync>,
 }
 impl Formatter {
     pub fn builder() -> FormatterBuilder<WithNothing> {
@@ -86,25 +139,74 @@ impl Formatter {
         out
     }
 
-    fn format_into_buf(&self, input: &str, buf: &mut String, options: FileOptions) {
+    /// Lexes the input, applying all raw token consolidators.
+    pub fn lex<'a>(&self, input: &'a str) -> Vec<RawToken<'a>> {
         let mut tokens = self.lexer.lex(input);
-        let mut cursors = self.reconstructor.process_cursors(options.cursors, &tokens);
-
         for token_consolidator in self.token_consolidators.iter() {
             token_consolidator.consolidate(&mut tokens);
         }
+        tokens
+    }
+
+    /// Lexes and parses the input into logical lines, applying all
+    /// consolidators.
+    ///
+    /// This is the state of the input before any tokens are ignored or
+    /// removed, and before any formatting takes place.
+    pub fn parse<'a>(&self, input: &'a str) -> (Vec<LogicalLine>, Vec<Token<'a>>) {
+        self.parse_tokens(self.lex(input))
+    }
+
+    fn parse_tokens<'a>(&self, tokens: Vec<RawToken<'a>>) -> (Vec<LogicalLine>, Vec<Token<'a>>) {
         let (mut lines, mut tokens) = self.logical_line_parser.parse(tokens);
         for line_consolidator in self.post_parse_consolidators.iter() {
             line_consolidator.consolidate((&mut tokens, &mut lines));
         }
-        let mut ignored_tokens = TokenMarker::default();
-        for token_ignorer in &self.token_ignorers {
-            token_ignorer.ignore_tokens((&tokens, &lines), &mut ignored_tokens)
+        (lines, tokens)
+    }
+
+    fn format_into_buf(&self, input: &str, buf: &mut String, options: FileOptions) {
+        let mut timer = StageTimer {
+            stage_timings: options.stage_timings,
+        };
+
+        let mut tokens = timer.time("lex", || self.lexer.lex(input));
+        // Cursors are placed before any tokens are reordered, so that they
+        // refer to the positions in the input.
+        let mut cursors = self.reconstructor.process_cursors(options.cursors, &tokens);
+        let mut origins: Vec<usize> = (0..tokens.len()).collect();
+        timer.time("consolidate raw tokens", || {
+            for token_consolidator in self.token_consolidators.iter() {
+                token_consolidator.consolidate_with_origins(&mut tokens, &mut origins);
+            }
+        });
+        let tokens_moved = origins
+            .iter()
+            .enumerate()
+            .any(|(index, &origin)| index != origin);
+        if tokens_moved {
+            cursors.notify_tokens_moved(&origins);
         }
+
+        let (mut lines, mut tokens) =
+            timer.time("parse", || self.logical_line_parser.parse(tokens));
+        timer.time("consolidate lines", || {
+            for line_consolidator in self.post_parse_consolidators.iter() {
+                line_consolidator.consolidate((&mut tokens, &mut lines));
+            }
+        });
+        let mut ignored_tokens = TokenMarker::default();
+        timer.time("ignore tokens", || {
+            for token_ignorer in &self.token_ignorers {
+                token_ignorer.ignore_tokens((&tokens, &lines), &mut ignored_tokens)
+            }
+        });
         let mut tokens_marked_for_deletion = TokenMarker::default();
-        for token_remover in self.token_removers.iter() {
-            token_remover.remove_tokens((&tokens, &lines), &mut tokens_marked_for_deletion);
-        }
+        timer.time("remove tokens", || {
+            for token_remover in self.token_removers.iter() {
+                token_remover.remove_tokens((&tokens, &lines), &mut tokens_marked_for_deletion);
+            }
+        });
 
         if ignored_tokens.any_marked() {
             tokens_marked_for_deletion
@@ -119,6 +221,14 @@ impl Formatter {
             }
         }
 
+        if tokens_moved {
+            let mut index = 0;
+            origins.retain(|_| {
+                let keep = !tokens_marked_for_deletion.is_marked(&index);
+                index += 1;
+                keep
+            });
+        }
         delete_marked_tokens(
             tokens_marked_for_deletion,
             &mut tokens,
@@ -127,13 +237,21 @@ impl Formatter {
         );
 
         let mut formatted_tokens = FormattedTokens::new_from_tokens(&mut tokens, &ignored_tokens);
-        for formatter in self.logical_line_formatters.iter() {
-            formatter.format(&mut formatted_tokens, &lines);
+        if tokens_moved {
+            formatted_tokens.set_origins(origins);
+        }
+        for (name, formatter) in self.logical_line_formatters.iter() {
+            timer.time(name, || formatter.format(&mut formatted_tokens, &lines));
         }
 
         cursors.relocate_cursors(&formatted_tokens);
+        if let Some(diagnostics) = options.diagnostics {
+            diagnostics.append(&mut formatted_tokens.take_diagnostics());
+        }
 
-        self.reconstructor.reconstruct(formatted_tokens, buf);
+        timer.time("reconstruct", || {
+            self.reconstructor.reconstruct(formatted_tokens, buf)
+        });
     }
 }
 
@@ -221,54 +339,54 @@ builder_state!(WithFormatter: [CanAddFormatter, CanAddReconstructor]);
 builder_state!(WithReconstructor: [CanBuild]);
 
 pub trait AddLexer {
-    fn lexer<T: Lexer + Sync + 'static>(self, lexer: T) -> FormatterBuilder<WithLexer>;
+    fn lexer<T: Lexer + Send + Sync + 'static>(self, lexer: T) -> FormatterBuilder<WithLexer>;
 }
 pub trait AddRawTokenConsolidator {
-    fn raw_token_consolidator<T: RawTokenConsolidator + Sync + 'static>(
+    fn raw_token_consolidator<T: RawTokenConsolidator + Send + Sync + 'static>(
         self,
         token_consolidator: T,
     ) -> FormatterBuilder<WithRawTokenConsolidator>;
 }
 pub trait AddParser {
-    fn parser<T: LogicalLineParser + Sync + 'static>(
+    fn parser<T: LogicalLineParser + Send + Sync + 'static>(
         self,
         parser: T,
     ) -> FormatterBuilder<WithParser>;
 }
 pub trait AddPostParseConsolidator {
-    fn token_consolidator<T: TokenConsolidator + Sync + 'static>(
+    fn token_consolidator<T: TokenConsolidator + Send + Sync + 'static>(
         self,
         token_consolidator: T,
     ) -> FormatterBuilder<WithLinesConsolidator>;
-    fn lines_consolidator<T: LogicalLinesConsolidator + Sync + 'static>(
+    fn lines_consolidator<T: LogicalLinesConsolidator + Send + Sync + 'static>(
         self,
         lines_consolidator: T,
     ) -> FormatterBuilder<WithLinesConsolidator>;
 }
 pub trait AddTokenIgnorer {
-    fn token_ignorer<T: TokenIgnorer + Sync + 'static>(
+    fn token_ignorer<T: TokenIgnorer + Send + Sync + 'static>(
         self,
         token_ignorer: T,
     ) -> FormatterBuilder<WithTokenIgnorer>;
 }
 pub trait AddTokenRemover {
-    fn token_remover<T: TokenRemover + Sync + 'static>(
+    fn token_remover<T: TokenRemover + Send + Sync + 'static>(
         self,
         token_remover: T,
     ) -> FormatterBuilder<WithTokenRemover>;
 }
 pub trait AddFormatter {
-    fn line_formatter<T: LogicalLineFormatter + Sync + 'static>(
+    fn line_formatter<T: LogicalLineFormatter + Send + Sync + 'static>(
         self,
         formatter: T,
     ) -> FormatterBuilder<WithFormatter>;
-    fn file_formatter<T: LogicalLineFileFormatter + Sync + 'static>(
+    fn file_formatter<T: LogicalLineFileFormatter + Send + Sync + 'static>(
         self,
         formatter: T,
     ) -> FormatterBuilder<WithFormatter>;
 }
 pub trait AddReconstructor {
-    fn reconstructor<T: LogicalLinesReconstructor + Sync + 'static>(
+    fn reconstructor<T: LogicalLinesReconstructor + Send + Sync + 'static>(
         self,
         reconstructor: T,
     ) -> FormatterBuilder<WithReconstructor>;
@@ -279,29 +397,32 @@ pub trait BuildFormatter {
 
 #[derive(Default)]
 pub struct FormatterBuilder<T> {
-    lexer: Option<Box<dyn Lexer + Sync>>,
-    token_consolidators: Vec<Box<dyn RawTokenConsolidator + Sync + 'static>>,
-    logical_line_parser: Option<Box<dyn LogicalLineParser + Sync + 'static>>,
+    lexer: Option<Box<dyn Lexer + Send + Sync>>,
+    token_consolidators: Vec<Box<dyn RawTokenConsolidator + Send + Sync + 'static>>,
+    logical_line_parser: Option<Box<dyn LogicalLineParser + Send + Sync + 'static>>,
     post_parse_consolidators: Vec<PostParseConsolidatorKind>,
-    token_ignorers: Vec<Box<dyn TokenIgnorer + Sync + 'static>>,
-    token_removers: Vec<Box<dyn TokenRemover + Sync + 'static>>,
-    logical_line_formatters: Vec<FormatterKind>,
-    reconstructor: Option<Box<dyn LogicalLinesReconstructor + Sync + 'static>>,
+    token_ignorers: Vec<Box<dyn TokenIgnorer + Send + Sync + 'static>>,
+    token_removers: Vec<Box<dyn TokenRemover + Send + Sync + 'static>>,
+    logical_line_formatters: Vec<(&'static str, FormatterKind)>,
+    reconstructor: Option<Box<dyn LogicalLinesReconstructor + Send + Sync + 'static>>,
     builder_state: PhantomData<T>,
 }
 impl<T> FormatterBuilder<T> {
-    fn set_lexer<L: Lexer + Sync + 'static>(mut self, lexer: L) -> Self {
+    fn set_lexer<L: Lexer + Send + Sync + 'static>(mut self, lexer: L) -> Self {
         self.lexer = Some(Box::new(lexer));
         self
     }
-    fn add_raw_token_consolidator<C: RawTokenConsolidator + Sync + 'static>(
+    fn add_raw_token_consolidator<C: RawTokenConsolidator + Send + Sync + 'static>(
         mut self,
         token_consolidator: C,
     ) -> Self {
         self.token_consolidators.push(Box::new(token_consolidator));
         self
     }
-    fn set_line_parser<P: LogicalLineParser + Sync + 'static>(mut self, line_parser: P) -> Self {
+    fn set_line_parser<P: LogicalLineParser + Send + Sync + 'static>(
+        mut self,
+        line_parser: P,
+    ) -> Self {
         self.logical_line_parser = Some(Box::new(line_parser));
         self
     }
@@ -312,19 +433,26 @@ impl<T> FormatterBuilder<T> {
         self.post_parse_consolidators.push(post_parse_consolidator);
         self
     }
-    fn add_token_ignorer<R: TokenIgnorer + Sync + 'static>(mut self, token_ignorer: R) -> Self {
+    fn add_token_ignorer<R: TokenIgnorer + Send + Sync + 'static>(
+        mut self,
+        token_ignorer: R,
+    ) -> Self {
         self.token_ignorers.push(Box::new(token_ignorer));
         self
     }
-    fn add_token_remover<R: TokenRemover + Sync + 'static>(mut self, token_remover: R) -> Self {
+    fn add_token_remover<R: TokenRemover + Send + Sync + 'static>(
+        mut self,
+        token_remover: R,
+    ) -> Self {
         self.token_removers.push(Box::new(token_remover));
         self
     }
-    fn add_formatter(mut self, logical_line_formatter: FormatterKind) -> Self {
-        self.logical_line_formatters.push(logical_line_formatter);
+    fn add_formatter(mut self, name: &'static str, logical_line_formatter: FormatterKind) -> Self {
+        self.logical_line_formatters
+            .push((name, logical_line_formatter));
         self
     }
-    fn set_reconstructor<R: LogicalLinesReconstructor + Sync + 'static>(
+    fn set_reconstructor<R: LogicalLinesReconstructor + Send + Sync + 'static>(
         mut self,
         reconstructor: R,
     ) -> Self {
@@ -348,12 +476,12 @@ impl<T> FormatterBuilder<T> {
 }
 
 impl<U: CanAddLexer> AddLexer for FormatterBuilder<U> {
-    fn lexer<T: Lexer + Sync + 'static>(self, lexer: T) -> FormatterBuilder<WithLexer> {
+    fn lexer<T: Lexer + Send + Sync + 'static>(self, lexer: T) -> FormatterBuilder<WithLexer> {
         self.set_lexer(lexer).convert_type()
     }
 }
 impl<U: CanAddRawTokenConsolidator> AddRawTokenConsolidator for FormatterBuilder<U> {
-    fn raw_token_consolidator<T: RawTokenConsolidator + Sync + 'static>(
+    fn raw_token_consolidator<T: RawTokenConsolidator + Send + Sync + 'static>(
         self,
         token_consolidator: T,
     ) -> FormatterBuilder<WithRawTokenConsolidator> {
@@ -362,7 +490,7 @@ impl<U: CanAddRawTokenConsolidator> AddRawTokenConsolidator for FormatterBuilder
     }
 }
 impl<U: CanAddParser> AddParser for FormatterBuilder<U> {
-    fn parser<T: LogicalLineParser + Sync + 'static>(
+    fn parser<T: LogicalLineParser + Send + Sync + 'static>(
         self,
         parser: T,
     ) -> FormatterBuilder<WithParser> {
@@ -370,7 +498,7 @@ impl<U: CanAddParser> AddParser for FormatterBuilder<U> {
     }
 }
 impl<U: CanAddPostParseConsolidator> AddPostParseConsolidator for FormatterBuilder<U> {
-    fn token_consolidator<T: TokenConsolidator + Sync + 'static>(
+    fn token_consolidator<T: TokenConsolidator + Send + Sync + 'static>(
         self,
         token_consolidator: T,
     ) -> FormatterBuilder<WithLinesConsolidator> {
@@ -379,7 +507,7 @@ impl<U: CanAddPostParseConsolidator> AddPostParseConsolidator for FormatterBuild
         )))
         .convert_type()
     }
-    fn lines_consolidator<T: LogicalLinesConsolidator + Sync + 'static>(
+    fn lines_consolidator<T: LogicalLinesConsolidator + Send + Sync + 'static>(
         self,
         lines_consolidator: T,
     ) -> FormatterBuilder<WithLinesConsolidator> {
@@ -390,7 +518,7 @@ impl<U: CanAddPostParseConsolidator> AddPostParseConsolidator for FormatterBuild
     }
 }
 impl<U: CanAddTokenIgnorer> AddTokenIgnorer for FormatterBuilder<U> {
-    fn token_ignorer<T: TokenIgnorer + Sync + 'static>(
+    fn token_ignorer<T: TokenIgnorer + Send + Sync + 'static>(
         self,
         token_ignorer: T,
     ) -> FormatterBuilder<WithTokenIgnorer> {
@@ -398,7 +526,7 @@ impl<U: CanAddTokenIgnorer> AddTokenIgnorer for FormatterBuilder<U> {
     }
 }
 impl<U: CanAddTokenRemover> AddTokenRemover for FormatterBuilder<U> {
-    fn token_remover<T: TokenRemover + Sync + 'static>(
+    fn token_remover<T: TokenRemover + Send + Sync + 'static>(
         self,
         token_remover: T,
     ) -> FormatterBuilder<WithTokenRemover> {
@@ -406,24 +534,30 @@ impl<U: CanAddTokenRemover> AddTokenRemover for FormatterBuilder<U> {
     }
 }
 impl<U: CanAddFormatter> AddFormatter for FormatterBuilder<U> {
-    fn line_formatter<T: LogicalLineFormatter + Sync + 'static>(
+    fn line_formatter<T: LogicalLineFormatter + Send + Sync + 'static>(
         self,
         formatter: T,
     ) -> FormatterBuilder<WithFormatter> {
-        self.add_formatter(FormatterKind::LineFormatter(Box::new(formatter)))
-            .convert_type()
+        self.add_formatter(
+            short_type_name::<T>(),
+            FormatterKind::LineFormatter(Box::new(formatter)),
+        )
+        .convert_type()
     }
 
-    fn file_formatter<T: LogicalLineFileFormatter + Sync + 'static>(
+    fn file_formatter<T: LogicalLineFileFormatter + Send + Sync + 'static>(
         self,
         formatter: T,
     ) -> FormatterBuilder<WithFormatter> {
-        self.add_formatter(FormatterKind::FileFormatter(Box::new(formatter)))
-            .convert_type()
+        self.add_formatter(
+            short_type_name::<T>(),
+            FormatterKind::FileFormatter(Box::new(formatter)),
+        )
+        .convert_type()
     }
 }
 impl<U: CanAddReconstructor> AddReconstructor for FormatterBuilder<U> {
-    fn reconstructor<T: LogicalLinesReconstructor + Sync + 'static>(
+    fn reconstructor<T: LogicalLinesReconstructor + Send + Sync + 'static>(
         self,
         reconstructor: T,
     ) -> FormatterBuilder<WithReconstructor> {
@@ -1207,4 +1341,44 @@ else
             },
         );
     }
+
+    #[test]
+    fn stage_timings() {
+        let formatter = Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .line_formatter(RetainSpacesLogcialLinesOnNewLines {})
+            .file_formatter(IndentSecondLine3SpacesIfNoNewLine {})
+            .reconstructor(default_test_reconstructor())
+            .build();
+
+        let mut stage_timings = vec![];
+        formatter.format(
+            "a;b;",
+            FileOptions::new().with_stage_timings(&mut stage_timings),
+        );
+        assert_that(
+            &stage_timings
+                .iter()
+                .map(|timing| timing.stage)
+                .collect_vec(),
+        )
+        .is_equal_to(vec![
+            "lex",
+            "consolidate raw tokens",
+            "parse",
+            "consolidate lines",
+            "ignore tokens",
+            "remove tokens",
+            "RetainSpacesLogcialLinesOnNewLines",
+            "IndentSecondLine3SpacesIfNoNewLine",
+            "reconstruct",
+        ]);
+        assert_that(
+            &stage_timings
+                .windows(2)
+                .all(|pair| pair[0].start <= pair[1].start),
+        )
+        .is_true();
+    }
 }
@@ -181,6 +181,74 @@ pub enum KeywordKind {
     WriteOnly,
 }
 impl KeywordKind {
+    /// Whether the keyword is impure, i.e. it is only a keyword in certain
+    /// contexts (e.g. `override` or `absolute`), as opposed to a reserved word.
+    pub fn is_impure(&self) -> bool {
+        matches!(
+            self,
+            KeywordKind::Absolute
+                | KeywordKind::Abstract
+                | KeywordKind::Align
+                | KeywordKind::Assembler
+                | KeywordKind::At
+                | KeywordKind::Automated
+                | KeywordKind::Cdecl
+                | KeywordKind::Contains
+                | KeywordKind::Default
+                | KeywordKind::Delayed
+                | KeywordKind::Deprecated
+                | KeywordKind::DispId
+                | KeywordKind::Dynamic
+                | KeywordKind::Experimental
+                | KeywordKind::Export
+                | KeywordKind::External
+                | KeywordKind::Far
+                | KeywordKind::Final
+                | KeywordKind::Forward
+                | KeywordKind::Helper
+                | KeywordKind::Implements
+                | KeywordKind::Index
+                | KeywordKind::Local
+                | KeywordKind::Message
+                | KeywordKind::Name
+                | KeywordKind::Near
+                | KeywordKind::NoDefault
+                | KeywordKind::NoReturn
+                | KeywordKind::On
+                | KeywordKind::Operator
+                | KeywordKind::Out
+                | KeywordKind::Overload
+                | KeywordKind::Override
+                | KeywordKind::Package
+                | KeywordKind::Pascal
+                | KeywordKind::Platform
+                | KeywordKind::Private
+                | KeywordKind::Protected
+                | KeywordKind::Public
+                | KeywordKind::Published
+                | KeywordKind::Read
+                | KeywordKind::ReadOnly
+                | KeywordKind::Reference
+                | KeywordKind::Register
+                | KeywordKind::Reintroduce
+                | KeywordKind::Requires
+                | KeywordKind::Resident
+                | KeywordKind::SafeCall
+                | KeywordKind::Sealed
+                | KeywordKind::Static
+                | KeywordKind::StdCall
+                | KeywordKind::Stored
+                | KeywordKind::Strict
+                | KeywordKind::Unmanaged
+                | KeywordKind::Unsafe
+                | KeywordKind::VarArgs
+                | KeywordKind::Virtual
+                | KeywordKind::WinApi
+                | KeywordKind::Write
+                | KeywordKind::WriteOnly
+        )
+    }
+
     pub fn is_method_directive(&self) -> bool {
         matches!(
             self,
@@ -569,11 +637,48 @@ pub enum MutTokenErr {
     TokenIgnored,
 }
 
+/// Something notable that happened while formatting a file, reported against a
+/// line of the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The 1-based line number in the input.
+    pub line_number: u32,
+    pub kind: DiagnosticKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiagnosticKind {
+    /// The [`OptimisingLineFormatter`] exceeded its iteration limit, so the
+    /// line was formatted using the fallback strategy.
+    IterationLimitReached {
+        iteration_max: u32,
+        fallback: IterationLimitFallback,
+    },
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            DiagnosticKind::IterationLimitReached {
+                iteration_max,
+                fallback,
+            } => write!(
+                f,
+                "iteration limit ({iteration_max}) reached, formatted using the `{fallback}` fallback"
+            ),
+        }
+    }
+}
+
 pub struct FormattedTokens<'a> {
     tokens: &'a mut [Token<'a>],
     /// Formatting metadata for each token, with the invariant that the length
     /// will always match the length of [field@FormattedTokens::tokens]
     fmt: Vec<FormattingData>,
+    /// The position of each token in the input, if the tokens were reordered
+    /// before parsing.
+    origins: Option<Vec<usize>>,
+    diagnostics: Vec<Diagnostic>,
 }
 impl<'a> FormattedTokens<'a> {
     pub fn new_from_tokens(tokens: &'a mut [Token<'a>], ignored_tokens: &TokenMarker) -> Self {
@@ -584,7 +689,12 @@ impl<'a> FormattedTokens<'a> {
                 FormattingData::from((token.get_leading_whitespace(), ignored_tokens.is_marked(&i)))
             })
             .collect();
-        FormattedTokens { tokens, fmt }
+        FormattedTokens {
+            tokens,
+            fmt,
+            origins: None,
+            diagnostics: vec![],
+        }
     }
 
     #[cfg(test)]
@@ -598,6 +708,8 @@ impl<'a> FormattedTokens<'a> {
         Self {
             tokens,
             fmt: formatting_data,
+            origins: None,
+            diagnostics: vec![],
         }
     }
 
@@ -663,6 +775,27 @@ impl<'a> FormattedTokens<'a> {
     pub fn get_token_type_for_index(&self, index: usize) -> Option<TokenType> {
         self.tokens.get(index).map(|t| t.get_token_type())
     }
+    /// The indices of the tokens in the order they appear in the input.
+    ///
+    /// This differs from the order of the tokens when they were reordered
+    /// before parsing, e.g. by [`SortUses`](crate::rules::sort_uses::SortUses).
+    pub fn input_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.tokens.len()).collect();
+        if let Some(origins) = &self.origins {
+            order.sort_by_key(|&index| origins[index]);
+        }
+        order
+    }
+    pub(crate) fn set_origins(&mut self, origins: Vec<usize>) {
+        debug_assert_eq!(origins.len(), self.tokens.len());
+        self.origins = Some(origins);
+    }
+    pub fn add_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        std::mem::take(&mut self.diagnostics)
+    }
 }
 
 pub struct LogicalLines<'a> {
@@ -864,8 +997,8 @@ impl<'a> From<RawToken<'a>> for Token<'a> {
 }
 
 pub enum FormatterKind {
-    LineFormatter(Box<dyn LogicalLineFormatter + Sync>),
-    FileFormatter(Box<dyn LogicalLineFileFormatter + Sync>),
+    LineFormatter(Box<dyn LogicalLineFormatter + Send + Sync>),
+    FileFormatter(Box<dyn LogicalLineFileFormatter + Send + Sync>),
 }
 impl LogicalLineFileFormatter for FormatterKind {
     fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, input: &[LogicalLine]) {
@@ -0,0 +1,465 @@
+use crate::lang::OperatorKind as OK;
+use crate::lang::TokenType as TT;
+use crate::prelude::*;
+
+/// Aligns the `:`, `=` or `:=` of consecutive declarations, e.g.
+/// ```delphi
+/// var
+///   Count    : Integer;
+///   FirstName: string;
+/// ```
+///
+/// Consecutive `Declaration` or `InlineDeclaration` lines at the same level
+/// are aligned together. Blank lines which are kept within `max_blank_lines`
+/// and any other lines, including comments and lines where formatting is
+/// turned off, separate these groups. Lines where the aligned token doesn't
+/// fit within `max_line_length`, either as they are or once aligned, are left
+/// out of their group.
+///
+/// Groups don't depend on where the input lines are wrapped, so that
+/// formatting the output again gives the same alignment.
+///
+/// This must run after [`TokenSpacing`] and before the
+/// [`OptimisingLineFormatter`], which then accounts for the alignment when
+/// wrapping lines.
+pub struct AlignDeclarations {
+    max_line_length: u32,
+    max_blank_lines: u16,
+    recon_settings: ReconstructionSettings,
+}
+
+impl AlignDeclarations {
+    pub fn new(
+        max_line_length: u32,
+        max_blank_lines: u16,
+        recon_settings: ReconstructionSettings,
+    ) -> Self {
+        AlignDeclarations {
+            max_line_length,
+            max_blank_lines,
+            recon_settings,
+        }
+    }
+}
+
+struct AlignedLine {
+    /// The token to be aligned.
+    token_index: usize,
+    /// The width of the line's indentation.
+    indentation: usize,
+    /// The width of the line up to the aligned token, including the spaces
+    /// before it.
+    width: usize,
+    /// The width of the aligned token.
+    token_width: usize,
+}
+
+impl AlignDeclarations {
+    fn aligned_line(
+        &self,
+        formatted_tokens: &FormattedTokens,
+        line: &LogicalLine,
+    ) -> Option<AlignedLine> {
+        let mut width = 0;
+        let mut depth = 0;
+        for (i, &token_index) in line.get_tokens().iter().enumerate() {
+            let (token, formatting_data) = formatted_tokens.get_token(token_index)?;
+            if formatting_data.is_ignored() {
+                return None;
+            }
+            if i > 0 {
+                width += formatting_data.spaces_before as usize;
+            }
+            let token_width = token.get_content().chars().count();
+            match token.get_token_type() {
+                TT::Op(OK::Colon | OK::Assign | OK::Equal(EqKind::Decl)) if depth == 0 && i > 0 => {
+                    return Some(AlignedLine {
+                        token_index,
+                        indentation: line.get_level() as usize
+                            * self.recon_settings.get_indentation_str().len(),
+                        width,
+                        token_width,
+                    });
+                }
+                TT::Op(OK::LParen | OK::LBrack | OK::LessThan(ChevronKind::Generic)) => depth += 1,
+                TT::Op(OK::RParen | OK::RBrack | OK::GreaterThan(ChevronKind::Generic)) => {
+                    depth -= 1
+                }
+                TT::Comment(_) | TT::CompilerDirective | TT::ConditionalDirective(_) => {
+                    return None;
+                }
+                _ => {}
+            }
+            width += token_width;
+        }
+        None
+    }
+
+    fn fits(&self, line: &AlignedLine, column: usize) -> bool {
+        line.indentation + column + line.token_width <= self.max_line_length as usize
+    }
+
+    fn align_group(&self, formatted_tokens: &mut FormattedTokens, group: &mut Vec<AlignedLine>) {
+        // Lines which would be wrapped before the aligned token don't set the
+        // column, and lines it would push past `max_line_length` aren't
+        // aligned.
+        group.retain(|line| self.fits(line, line.width));
+        let column = loop {
+            let column = group.iter().map(|line| line.width).max().unwrap_or(0);
+            let group_len = group.len();
+            group.retain(|line| self.fits(line, column));
+            if group.len() == group_len {
+                break column;
+            }
+        };
+        if group.len() < 2 {
+            return;
+        }
+
+        for line in group.iter() {
+            if let Some(formatting_data) =
+                formatted_tokens.get_formatting_data_mut(line.token_index)
+            {
+                let padding = (column - line.width) as u16;
+                formatting_data.spaces_before =
+                    formatting_data.spaces_before.saturating_add(padding);
+            }
+        }
+    }
+}
+
+fn is_aligned_line_type(line_type: LogicalLineType) -> bool {
+    matches!(
+        line_type,
+        LogicalLineType::Declaration | LogicalLineType::InlineDeclaration
+    )
+}
+
+impl AlignDeclarations {
+    /// Whether `line` continues the group of declarations that `previous` is
+    /// in, i.e. there won't be a blank line between them once formatted.
+    fn continues_group(
+        &self,
+        formatted_tokens: &FormattedTokens,
+        previous: &LogicalLine,
+        line: &LogicalLine,
+    ) -> bool {
+        line.get_line_type() == previous.get_line_type()
+            && line.get_level() == previous.get_level()
+            && line.get_parent() == previous.get_parent()
+            && line
+                .get_tokens()
+                .first()
+                .and_then(|&token_index| formatted_tokens.get_formatting_data(token_index))
+                .is_some_and(|formatting_data| {
+                    formatting_data.newlines_before < 2 || self.max_blank_lines == 0
+                })
+    }
+}
+
+impl LogicalLineFileFormatter for AlignDeclarations {
+    fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, input: &[LogicalLine]) {
+        let mut group: Vec<AlignedLine> = vec![];
+        let mut previous: Option<&LogicalLine> = None;
+        for line in input.iter().filter(|line| {
+            // Voided lines are kept, as they separate groups like comments.
+            matches!(line.get_line_type(), LogicalLineType::Voided) || !line.get_tokens().is_empty()
+        }) {
+            let aligned = is_aligned_line_type(line.get_line_type())
+                .then(|| self.aligned_line(formatted_tokens, line))
+                .flatten();
+
+            let continues = previous
+                .is_some_and(|previous| self.continues_group(formatted_tokens, previous, line));
+            if !continues || aligned.is_none() {
+                self.align_group(formatted_tokens, &mut group);
+                group.clear();
+            }
+
+            match aligned {
+                Some(aligned) => {
+                    group.push(aligned);
+                    previous = Some(line);
+                }
+                None => previous = None,
+            }
+        }
+        self.align_group(formatted_tokens, &mut group);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn formatter() -> Formatter {
+        formatter_with(1)
+    }
+
+    fn formatter_with(max_blank_lines: u16) -> Formatter {
+        let recon = default_test_reconstruction_settings();
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .token_consolidator(DistinguishGenericTypeParamsConsolidator {})
+            .token_ignorer(FormattingToggler {})
+            .file_formatter(TokenSpacing {})
+            .file_formatter(AlignDeclarations::new(40, max_blank_lines, recon.clone()))
+            .file_formatter(OptimisingLineFormatter::new(
+                OptimisingLineFormatterSettings {
+                    max_line_length: 40,
+                    max_blank_lines,
+                    ..Default::default()
+                },
+                recon.clone(),
+            ))
+            .reconstructor(DelphiLogicalLinesReconstructor::new(recon))
+            .build()
+    }
+
+    formatter_test_group!(
+        tests,
+        var_section = {
+            indoc! {"
+                var
+                  A: Integer;
+                  FirstName: string;
+                  B, C: Boolean = False;
+            "},
+            indoc! {"
+                var
+                  A        : Integer;
+                  FirstName: string;
+                  B, C     : Boolean = False;
+            "},
+        },
+        const_section = {
+            indoc! {"
+                const
+                  A = 1;
+                  Longer = 2;
+                  Typed: Integer = 3;
+            "},
+            indoc! {"
+                const
+                  A      = 1;
+                  Longer = 2;
+                  Typed  : Integer = 3;
+            "},
+        },
+        type_section = {
+            indoc! {"
+                type
+                  TFoo<T> = TList<T>;
+                  TBarBaz = Integer;
+                  TProc = procedure;
+            "},
+            indoc! {"
+                type
+                  TFoo<T> = TList<T>;
+                  TBarBaz = Integer;
+                  TProc   = procedure;
+            "},
+        },
+        record_fields = {
+            indoc! {"
+                type
+                  TRec = record
+                    X: Integer;
+                    Name: string;
+                  end;
+            "},
+            indoc! {"
+                type
+                  TRec = record
+                    X   : Integer;
+                    Name: string;
+                  end;
+            "},
+        },
+        class_field_blocks = {
+            indoc! {"
+                type
+                  TFoo = class
+                  private
+                    FA: Integer;
+                    FName: string;
+                    procedure Foo;
+                    FBar: Integer;
+                  public
+                    FLonger: Integer;
+                  end;
+            "},
+            indoc! {"
+                type
+                  TFoo = class
+                  private
+                    FA   : Integer;
+                    FName: string;
+                    procedure Foo;
+                    FBar: Integer;
+                  public
+                    FLonger: Integer;
+                  end;
+            "},
+        },
+        inline_declarations = {
+            indoc! {"
+                begin
+                  var A := 1;
+                  var Longer: Integer := 2;
+                  Foo;
+                end;
+            "},
+            indoc! {"
+                begin
+                  var A     := 1;
+                  var Longer: Integer := 2;
+                  Foo;
+                end;
+            "},
+        },
+        groups_break_on_blank_lines = {
+            indoc! {"
+                var
+                  A: Integer;
+                  BB: Integer;
+
+                  CCCC: Integer;
+                  D: Integer;
+            "},
+            indoc! {"
+                var
+                  A : Integer;
+                  BB: Integer;
+
+                  CCCC: Integer;
+                  D   : Integer;
+            "},
+        },
+        groups_break_on_comments = {
+            indoc! {"
+                var
+                  A: Integer;
+                  BB: Integer;
+                  // comment
+                  CCCC: Integer;
+                  D: Integer;
+            "},
+            indoc! {"
+                var
+                  A : Integer;
+                  BB: Integer;
+                  // comment
+                  CCCC: Integer;
+                  D   : Integer;
+            "},
+        },
+        groups_break_on_ignored_lines = {
+            indoc! {"
+                var
+                  A: Integer;
+                  Bbbbbb: Integer;
+                  // pasfmt off
+                  X : Integer;
+                  // pasfmt on
+                  Cccccccc: string;
+                  D: string;
+            "},
+            indoc! {"
+                var
+                  A     : Integer;
+                  Bbbbbb: Integer;
+                  // pasfmt off
+                  X : Integer;
+                  // pasfmt on
+                  Cccccccc: string;
+                  D       : string;
+            "},
+        },
+        groups_ignore_input_wrapping = {
+            indoc! {"
+                var
+                  A: Integer;
+                  Bbbbbb
+                    : Integer;
+            "},
+            indoc! {"
+                var
+                  A     : Integer;
+                  Bbbbbb: Integer;
+            "},
+        },
+        trailing_comments_are_kept = {
+            indoc! {"
+                var
+                  A: Integer; // a
+                  BB: Integer; // b
+            "},
+            indoc! {"
+                var
+                  A : Integer; // a
+                  BB: Integer; // b
+            "},
+        },
+        padding_counts_towards_line_length = {
+            indoc! {"
+                const
+                  A = 'aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa';
+                  LongerName = 1;
+            "},
+            indoc! {"
+                const
+                  A          =
+                    'aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa';
+                  LongerName = 1;
+            "},
+        },
+        wrapped_lines_are_not_aligned = {
+            indoc! {"
+                var
+                  A: Integer;
+                  Looooooooooooong, Bbbbbbbbbbbbbbbbb, Ccccccc: Integer;
+                  XYZ: Integer;
+            "},
+            indoc! {"
+                var
+                  A  : Integer;
+                  Looooooooooooong,
+                  Bbbbbbbbbbbbbbbbb,
+                  Ccccccc: Integer;
+                  XYZ: Integer;
+            "},
+        },
+        single_declaration = {
+            "var\n  A: Integer;\n",
+            "var\n  A: Integer;\n",
+        },
+    );
+
+    mod no_blank_lines {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(0)
+        }
+
+        formatter_test_group!(
+            tests,
+            removed_blank_lines_do_not_break_groups = {
+                indoc! {"
+                    var
+                      A: Integer;
+
+                      BB: Integer;
+                "},
+                indoc! {"
+                    var
+                      A : Integer;
+                      BB: Integer;
+                "},
+            },
+        );
+    }
+}
@@ -0,0 +1,357 @@
+use crate::lang::TokenType as TT;
+use crate::prelude::*;
+
+/// Aligns the trailing comments of consecutive lines to a common column, e.g.
+/// ```delphi
+/// const
+///   Red = 1;    // the colour of blood
+///   Yellow = 2; // the colour of the sun
+/// ```
+///
+/// Runs of lines which end with a comment are broken by blank lines, lines
+/// without a trailing comment, and changes of indentation. Comments are not
+/// moved past `max_line_length`.
+///
+/// This must run after the [`OptimisingLineFormatter`], once the lines of the
+/// file are known.
+pub struct AlignTrailingComments {
+    max_line_length: u32,
+    recon_settings: ReconstructionSettings,
+}
+
+impl AlignTrailingComments {
+    pub fn new(max_line_length: u32, recon_settings: ReconstructionSettings) -> Self {
+        AlignTrailingComments {
+            max_line_length,
+            recon_settings,
+        }
+    }
+}
+
+struct TrailingComment {
+    token_index: usize,
+    /// The column the comment starts at.
+    column: usize,
+}
+
+/// A line of the formatted output.
+#[derive(Default)]
+struct PhysicalLine {
+    /// The indentations and continuations the line starts with, or `None` if
+    /// its layout isn't known, i.e. it contains ignored or multiline tokens.
+    indentation: Option<(u16, u16)>,
+    /// Whether the line is preceded by a blank line.
+    blank_before: bool,
+    /// The column at the end of the line.
+    column: usize,
+    /// The index of the last token on the line, and the column it starts at.
+    last_token: Option<(usize, usize)>,
+    /// Whether the line has more than one token.
+    has_code: bool,
+}
+
+impl AlignTrailingComments {
+    fn physical_lines(&self, formatted_tokens: &FormattedTokens) -> Vec<PhysicalLine> {
+        let mut lines: Vec<PhysicalLine> = vec![];
+        for (token_index, (token, formatting_data)) in formatted_tokens.tokens().enumerate() {
+            if token.get_token_type() == TT::Eof {
+                continue;
+            }
+
+            let (newlines_before, spaces_before) = match formatting_data.is_ignored() {
+                true => {
+                    let leading_whitespace = token.get_leading_whitespace();
+                    let last_line = leading_whitespace.rsplit('\n').next().unwrap_or_default();
+                    (
+                        leading_whitespace.matches('\n').count(),
+                        last_line.trim_start_matches('\r').len(),
+                    )
+                }
+                false => (
+                    formatting_data.newlines_before as usize,
+                    formatting_data.spaces_before as usize,
+                ),
+            };
+
+            let line = match lines.last_mut() {
+                Some(line) if newlines_before == 0 => {
+                    line.has_code = true;
+                    line.column += spaces_before;
+                    line
+                }
+                _ => {
+                    let indentations = formatting_data.indentations_before;
+                    let continuations = formatting_data.continuations_before;
+                    lines.push(PhysicalLine {
+                        indentation: Some((indentations, continuations)),
+                        blank_before: newlines_before > 1,
+                        column: indentations as usize
+                            * self.recon_settings.get_indentation_str().len()
+                            + continuations as usize
+                                * self.recon_settings.get_continuation_str().len()
+                            + spaces_before,
+                        ..Default::default()
+                    });
+                    lines.last_mut().unwrap()
+                }
+            };
+
+            line.last_token = Some((token_index, line.column));
+            let content = token.get_content();
+            match content.rsplit_once('\n') {
+                Some((_, last_line)) => {
+                    line.column = last_line.chars().count();
+                    line.indentation = None;
+                }
+                None => line.column += content.chars().count(),
+            }
+            if formatting_data.is_ignored() {
+                line.indentation = None;
+            }
+        }
+        lines
+    }
+
+    fn align_run(&self, formatted_tokens: &mut FormattedTokens, run: &[TrailingComment]) {
+        if run.len() < 2 {
+            return;
+        }
+
+        let max_column = self.max_line_length as usize;
+        let Some(column) = run
+            .iter()
+            .map(|comment| comment.column)
+            .filter(|&column| column <= max_column)
+            .max()
+        else {
+            return;
+        };
+
+        for comment in run.iter().filter(|comment| comment.column < column) {
+            if let Some(formatting_data) =
+                formatted_tokens.get_formatting_data_mut(comment.token_index)
+            {
+                let padding = (column - comment.column) as u16;
+                formatting_data.spaces_before =
+                    formatting_data.spaces_before.saturating_add(padding);
+            }
+        }
+    }
+}
+
+impl LogicalLineFileFormatter for AlignTrailingComments {
+    fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, _input: &[LogicalLine]) {
+        let lines = self.physical_lines(formatted_tokens);
+
+        let mut run: Vec<TrailingComment> = vec![];
+        let mut run_indentation = None;
+        for line in lines {
+            let comment = line
+                .last_token
+                .filter(|&(token_index, _)| {
+                    line.has_code
+                        && matches!(
+                            formatted_tokens.get_token_type_for_index(token_index),
+                            Some(TT::Comment(
+                                CommentKind::InlineLine | CommentKind::InlineBlock
+                            ))
+                        )
+                })
+                .zip(line.indentation);
+
+            let continues_run = !line.blank_before
+                && comment.is_some_and(|(_, indentation)| run_indentation == Some(indentation));
+            if !continues_run {
+                self.align_run(formatted_tokens, &run);
+                run.clear();
+            }
+
+            run_indentation = comment.map(|(_, indentation)| indentation);
+            if let Some(((token_index, column), _)) = comment {
+                run.push(TrailingComment {
+                    token_index,
+                    column,
+                });
+            }
+        }
+        self.align_run(formatted_tokens, &run);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn formatter() -> Formatter {
+        let recon = default_test_reconstruction_settings();
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .token_consolidator(DistinguishGenericTypeParamsConsolidator {})
+            .token_ignorer(FormattingToggler {})
+            .file_formatter(TokenSpacing {})
+            .file_formatter(OptimisingLineFormatter::new(
+                OptimisingLineFormatterSettings {
+                    max_line_length: 30,
+                    ..Default::default()
+                },
+                recon.clone(),
+            ))
+            .file_formatter(AlignTrailingComments::new(30, recon.clone()))
+            .reconstructor(DelphiLogicalLinesReconstructor::new(recon))
+            .build()
+    }
+
+    formatter_test_group!(
+        tests,
+        line_comments = {
+            indoc! {"
+                const
+                  A = 1; // one
+                  Bbbb = 2; // two
+                  Cc = 3; // three
+            "},
+            indoc! {"
+                const
+                  A = 1;    // one
+                  Bbbb = 2; // two
+                  Cc = 3;   // three
+            "},
+        },
+        block_comments = {
+            indoc! {"
+                type
+                  TColour = (
+                    Red, { warm }
+                    Blue, { cold }
+                    Green { natural }
+                  );
+            "},
+            indoc! {"
+                type
+                  TColour = (
+                    Red,  { warm }
+                    Blue, { cold }
+                    Green { natural }
+                  );
+            "},
+        },
+        runs_break_on_blank_lines = {
+            indoc! {"
+                begin
+                  A; // a
+                  Bb; // b
+
+                  Ccc; // c
+                  D; // d
+                end;
+            "},
+            indoc! {"
+                begin
+                  A;  // a
+                  Bb; // b
+
+                  Ccc; // c
+                  D;   // d
+                end;
+            "},
+        },
+        runs_break_on_lines_without_comments = {
+            indoc! {"
+                begin
+                  A; // a
+                  Bb; // b
+                  Foo;
+                  Ccc; // c
+                  D; // d
+                end;
+            "},
+            indoc! {"
+                begin
+                  A;  // a
+                  Bb; // b
+                  Foo;
+                  Ccc; // c
+                  D;   // d
+                end;
+            "},
+        },
+        runs_break_on_indentation_changes = {
+            indoc! {"
+                begin
+                  A; // a
+                  if B then // b
+                    Ccc; // c
+                  D; // d
+                end;
+            "},
+            indoc! {"
+                begin
+                  A;        // a
+                  if B then // b
+                    Ccc; // c
+                  D; // d
+                end;
+            "},
+        },
+        individual_comments_are_not_aligned = {
+            indoc! {"
+                begin
+                  A; // a
+                  // b
+                  Bb; // c
+                end;
+            "},
+            indoc! {"
+                begin
+                  A; // a
+                  // b
+                  Bb; // c
+                end;
+            "},
+        },
+        column_is_capped_at_max_line_length = {
+            indoc! {"
+                begin
+                  A; // a
+                  Bbbbbbbbbbbbbbbbbbbbbbbbbbbbb; // b
+                  Cc; // c
+                end;
+            "},
+            indoc! {"
+                begin
+                  A;  // a
+                  Bbbbbbbbbbbbbbbbbbbbbbbbbbbbb; // b
+                  Cc; // c
+                end;
+            "},
+        },
+        ignored_lines_are_not_aligned = {
+            indoc! {"
+                begin
+                  A; // a
+                  // pasfmt off
+                  Bb;   // b
+                  // pasfmt on
+                  Ccc; // c
+                  D; // d
+                end;
+            "},
+            indoc! {"
+                begin
+                  A; // a
+                  // pasfmt off
+                  Bb;   // b
+                  // pasfmt on
+                  Ccc; // c
+                  D;   // d
+                end;
+            "},
+        },
+        single_comment = {
+            "begin\n  A; // a\nend;\n",
+            "begin\n  A; // a\nend;\n",
+        },
+    );
+}
@@ -0,0 +1,472 @@
+use crate::lang::TokenType as TT;
+use crate::prelude::*;
+
+/// Limits the number of consecutive blank lines, and requires blank lines
+/// between routine implementations and before the sections of a unit.
+///
+/// This must run after the [`OptimisingLineFormatter`], which keeps at most
+/// [`OptimisingLineFormatterSettings::max_blank_lines`] blank lines before
+/// each line.
+#[derive(Debug, Clone, Copy)]
+pub struct BlankLines {
+    /// The largest number of consecutive blank lines.
+    pub max_blank_lines: u16,
+    /// The least number of blank lines between routine implementations. This
+    /// takes precedence over `max_blank_lines`.
+    pub between_routines: u16,
+    /// The least number of blank lines before the `implementation`,
+    /// `initialization` and `finalization` sections. This takes precedence
+    /// over `max_blank_lines`.
+    pub before_sections: u16,
+    /// Whether to remove the blank lines right after `begin` and right before
+    /// any `end`, including the `end` of a `case` statement or a class or
+    /// record declaration.
+    pub trim_blocks: bool,
+}
+
+impl Default for BlankLines {
+    fn default() -> Self {
+        BlankLines {
+            max_blank_lines: 1,
+            between_routines: 0,
+            before_sections: 0,
+            trim_blocks: false,
+        }
+    }
+}
+
+fn first_keyword(formatted_tokens: &FormattedTokens, line: &LogicalLine) -> Option<KeywordKind> {
+    match line
+        .get_tokens()
+        .first()
+        .and_then(|&token_index| formatted_tokens.get_token_type_for_index(token_index))
+    {
+        Some(TT::Keyword(keyword)) => Some(keyword),
+        _ => None,
+    }
+}
+
+fn is_comment_line(formatted_tokens: &FormattedTokens, line: &LogicalLine) -> bool {
+    line.get_tokens().iter().all(|&token_index| {
+        matches!(
+            formatted_tokens.get_token_type_for_index(token_index),
+            Some(TT::Comment(_))
+        )
+    })
+}
+
+fn newlines_before(formatted_tokens: &FormattedTokens, line: &LogicalLine) -> u16 {
+    line.get_tokens()
+        .first()
+        .and_then(|&token_index| formatted_tokens.get_formatting_data(token_index))
+        .map(|formatting_data| formatting_data.newlines_before)
+        .unwrap_or_default()
+}
+
+impl BlankLines {
+    /// Finds the first token of each line which must be preceded by blank
+    /// lines, along with the least number of blank lines.
+    fn required_blank_lines(
+        &self,
+        formatted_tokens: &FormattedTokens,
+        input: &[LogicalLine],
+    ) -> Vec<(usize, u16)> {
+        let lines: Vec<&LogicalLine> = input
+            .iter()
+            .filter(|line| {
+                line.get_parent().is_none()
+                    && !matches!(line.get_line_type(), LogicalLineType::Voided)
+                    && !line.get_tokens().is_empty()
+            })
+            .collect();
+
+        let mut required = vec![];
+        for (index, line) in lines.iter().enumerate() {
+            let blank_lines = match (line.get_line_type(), first_keyword(formatted_tokens, line)) {
+                (LogicalLineType::RoutineHeader, _) => self.between_routines,
+                (
+                    LogicalLineType::Unknown,
+                    Some(
+                        KeywordKind::Implementation
+                        | KeywordKind::Initialization
+                        | KeywordKind::Finalization,
+                    ),
+                ) if line.get_level() == 0 => self.before_sections,
+                _ => continue,
+            };
+            if blank_lines == 0 {
+                continue;
+            }
+
+            // The blank lines go before any comments attached to the line.
+            let mut start = index;
+            while start > 0
+                && newlines_before(formatted_tokens, lines[start]) == 1
+                && is_comment_line(formatted_tokens, lines[start - 1])
+            {
+                start -= 1;
+            }
+            let Some(previous) = start.checked_sub(1).map(|previous| lines[previous]) else {
+                continue;
+            };
+
+            let follows_routine = first_keyword(formatted_tokens, previous)
+                == Some(KeywordKind::End)
+                && previous.get_level() == line.get_level();
+            if line.get_line_type() == LogicalLineType::RoutineHeader && !follows_routine {
+                continue;
+            }
+            required.push((lines[start].get_tokens()[0], blank_lines));
+        }
+        required
+    }
+}
+
+impl LogicalLineFileFormatter for BlankLines {
+    fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, input: &[LogicalLine]) {
+        let required = self.required_blank_lines(formatted_tokens, input);
+
+        let mut after_begin = false;
+        for (tok, formatting_data) in formatted_tokens.tokens_mut() {
+            let Ok(tok) = tok else {
+                after_begin = false;
+                continue;
+            };
+            let token_type = tok.get_token_type();
+            if token_type != TT::Eof {
+                let mut max_newlines = self.max_blank_lines.saturating_add(1);
+                if self.trim_blocks && (after_begin || token_type == TT::Keyword(KeywordKind::End))
+                {
+                    max_newlines = 1;
+                }
+                formatting_data.newlines_before = formatting_data.newlines_before.min(max_newlines);
+            }
+            after_begin = token_type == TT::Keyword(KeywordKind::Begin);
+        }
+
+        for (token_index, blank_lines) in required {
+            if let Some(formatting_data) = formatted_tokens.get_formatting_data_mut(token_index)
+                && !formatting_data.is_ignored()
+            {
+                formatting_data.newlines_before = formatting_data
+                    .newlines_before
+                    .max(blank_lines.saturating_add(1));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn formatter_with(blank_lines: BlankLines) -> Formatter {
+        let recon = default_test_reconstruction_settings();
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .token_consolidator(DistinguishGenericTypeParamsConsolidator {})
+            .token_ignorer(FormattingToggler {})
+            .file_formatter(TokenSpacing {})
+            .file_formatter(OptimisingLineFormatter::new(
+                OptimisingLineFormatterSettings {
+                    max_line_length: 40,
+                    max_blank_lines: blank_lines.max_blank_lines,
+                    ..Default::default()
+                },
+                recon.clone(),
+            ))
+            .file_formatter(blank_lines)
+            .reconstructor(DelphiLogicalLinesReconstructor::new(recon))
+            .build()
+    }
+
+    fn formatter() -> Formatter {
+        formatter_with(BlankLines::default())
+    }
+
+    formatter_test_group!(
+        default_settings,
+        single_blank_lines_are_kept = {
+            "a;\n\nb;\n",
+            "a;\n\nb;\n",
+        },
+        consecutive_blank_lines_are_limited = {
+            "a;\n\n\n\nb;\n",
+            "a;\n\nb;\n",
+        },
+        blank_lines_in_blocks_are_kept = {
+            "begin\n\n  a;\n\nend;\n",
+            "begin\n\n  a;\n\nend;\n",
+        },
+        missing_separators_are_not_added = {
+            "procedure A;\nbegin\nend;\nprocedure B;\nbegin\nend;\n",
+            "procedure A;\nbegin\nend;\nprocedure B;\nbegin\nend;\n",
+        },
+        ignored_blank_lines = {
+            "// pasfmt off\na;\n\n\n\nb;\n// pasfmt on\n",
+            "// pasfmt off\na;\n\n\n\nb;\n// pasfmt on\n",
+        },
+    );
+
+    mod max_blank_lines {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(BlankLines {
+                max_blank_lines: 2,
+                ..Default::default()
+            })
+        }
+
+        formatter_test_group!(
+            tests,
+            two_blank_lines_are_kept = {
+                "a;\n\n\nb;\n",
+                "a;\n\n\nb;\n",
+            },
+            consecutive_blank_lines_are_limited = {
+                "a;\n\n\n\n\nb;\n",
+                "a;\n\n\nb;\n",
+            },
+        );
+    }
+
+    mod no_blank_lines {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(BlankLines {
+                max_blank_lines: 0,
+                ..Default::default()
+            })
+        }
+
+        formatter_test_group!(
+            tests,
+            blank_lines_are_removed = {
+                "a;\n\nb;\n",
+                "a;\nb;\n",
+            },
+        );
+    }
+
+    mod between_routines {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(BlankLines {
+                between_routines: 2,
+                ..Default::default()
+            })
+        }
+
+        formatter_test_group!(
+            tests,
+            routine_implementations = {
+                indoc! {"
+                    procedure A;
+                    begin
+                    end;
+                    function B: Integer;
+                    var
+                      C: Integer;
+                    begin
+                    end;
+
+                    procedure TFoo.D;
+                    begin
+                    end;
+                "},
+                indoc! {"
+                    procedure A;
+                    begin
+                    end;
+
+
+                    function B: Integer;
+                    var
+                      C: Integer;
+                    begin
+                    end;
+
+
+                    procedure TFoo.D;
+                    begin
+                    end;
+                "},
+            },
+            nested_routines = {
+                indoc! {"
+                    procedure A;
+                      procedure B;
+                      begin
+                      end;
+                      procedure C;
+                      begin
+                      end;
+                    begin
+                    end;
+                "},
+                indoc! {"
+                    procedure A;
+                      procedure B;
+                      begin
+                      end;
+
+
+                      procedure C;
+                      begin
+                      end;
+                    begin
+                    end;
+                "},
+            },
+            blank_lines_go_before_comments = {
+                indoc! {"
+                    procedure A;
+                    begin
+                    end;
+                    // B does things
+                    { really }
+                    procedure B;
+                    begin
+                    end;
+                "},
+                indoc! {"
+                    procedure A;
+                    begin
+                    end;
+
+
+                    // B does things
+                    { really }
+                    procedure B;
+                    begin
+                    end;
+                "},
+            },
+            routine_declarations = {
+                indoc! {"
+                    type
+                      TFoo = class
+                        procedure A;
+                        procedure B;
+                      end;
+                    procedure C; forward;
+                    procedure D; forward;
+                "},
+                indoc! {"
+                    type
+                      TFoo = class
+                        procedure A;
+                        procedure B;
+                      end;
+                    procedure C; forward;
+                    procedure D; forward;
+                "},
+            },
+            more_blank_lines_are_kept = {
+                "procedure A;\nbegin\nend;\n\n\n\nprocedure B;\nbegin\nend;\n",
+                "procedure A;\nbegin\nend;\n\n\nprocedure B;\nbegin\nend;\n",
+            },
+        );
+    }
+
+    mod before_sections {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(BlankLines {
+                before_sections: 2,
+                ..Default::default()
+            })
+        }
+
+        formatter_test_group!(
+            tests,
+            sections = {
+                indoc! {"
+                    unit U;
+                    interface
+                    procedure A;
+                    // the implementation
+                    implementation
+                    procedure A;
+                    begin
+                    end;
+                    initialization
+                      A;
+                    finalization
+                      A;
+                    end.
+                "},
+                indoc! {"
+                    unit U;
+                    interface
+                    procedure A;
+
+
+                    // the implementation
+                    implementation
+                    procedure A;
+                    begin
+                    end;
+
+
+                    initialization
+                      A;
+
+
+                    finalization
+                      A;
+                    end.
+                "},
+            },
+        );
+    }
+
+    mod trim_blocks {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(BlankLines {
+                trim_blocks: true,
+                ..Default::default()
+            })
+        }
+
+        formatter_test_group!(
+            tests,
+            blank_lines_after_begin_and_before_end = {
+                indoc! {"
+                    begin
+
+                      a;
+
+                      if b then begin
+
+                        c;
+
+                      end;
+
+                    end;
+                "},
+                indoc! {"
+                    begin
+                      a;
+
+                      if b then begin
+                        c;
+                      end;
+                    end;
+                "},
+            },
+            blank_lines_before_other_ends = {
+                "type\n  TFoo = class\n    A: Integer;\n\n  end;\n",
+                "type\n  TFoo = class\n    A: Integer;\n  end;\n",
+            },
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use fxhash::FxHashMap;
+
+use crate::prelude::*;
+
+/// Rewrites each identifier that matches a word in the dictionary, ignoring
+/// case, to the canonical casing of that word.
+#[derive(Default)]
+pub struct CasingDictionary {
+    words: FxHashMap<String, String>,
+}
+
+impl CasingDictionary {
+    /// A list of the canonical spellings of common RTL and VCL identifiers,
+    /// e.g. `TStringList` and `FreeAndNil`.
+    pub const BUILTIN_WORD_LIST: &'static str = include_str!("builtin.txt");
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the words in `word_list`, which has one word per line. Empty lines
+    /// and lines starting with `#` are ignored.
+    ///
+    /// A word that is already in the dictionary is replaced.
+    pub fn add_words(&mut self, word_list: &str) {
+        let words = word_list
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty() && !word.starts_with('#'));
+        for word in words {
+            self.words.insert(word.to_lowercase(), word.to_owned());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+}
+
+impl LogicalLineFileFormatter for CasingDictionary {
+    fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, _input: &[LogicalLine]) {
+        for (tok, _) in formatted_tokens.tokens_mut() {
+            let Ok(tok) = tok else { continue };
+            if tok.get_token_type() != TokenType::Identifier {
+                continue;
+            }
+            if let Some(word) = self.words.get(&tok.get_content().to_lowercase())
+                && word != tok.get_content()
+            {
+                tok.set_content(word.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatter() -> Formatter {
+        let mut dictionary = CasingDictionary::new();
+        dictionary.add_words(CasingDictionary::BUILTIN_WORD_LIST);
+        dictionary.add_words("# house style\n\n  MyHelper  \nFREEANDNIL\n");
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .token_ignorer(FormattingToggler {})
+            .file_formatter(dictionary)
+            .reconstructor(default_test_reconstructor())
+            .build()
+    }
+
+    formatter_test_group!(
+        tests,
+        builtin_words = {
+            "var l: tstringlist; begin result := assigned(l); end;",
+            "var l: TStringList; begin Result := Assigned(l); end;",
+        },
+        words_from_list = {"myhelper(x);", "MyHelper(x);"},
+        later_words_replace_earlier = {"freeandnil(x);", "FREEANDNIL(x);"},
+        comments_are_ignored = {"house(x);", "house(x);"},
+        keywords_are_ignored = {"var s: STRING;", "var s: STRING;"},
+        strings_are_ignored = {"x := 'tstringlist';", "x := 'tstringlist';"},
+        ignored_tokens = {
+            "{pasfmt off} exit; {pasfmt on} exit;",
+            "{pasfmt off} exit; {pasfmt on} Exit;",
+        },
+    );
+}
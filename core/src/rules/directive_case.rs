@@ -0,0 +1,284 @@
+use crate::prelude::*;
+
+/// Sets the casing of compiler directives, with separate [`CaseStyle`]s for
+/// the directive names (e.g. `IFDEF`) and their arguments (e.g. `DEBUG`).
+///
+/// Directives have no known word boundaries, so [`CaseStyle::Pascal`] leaves
+/// them as they are. Quoted strings in the arguments are never changed.
+#[derive(Debug, Clone, Copy)]
+pub struct DirectiveCase {
+    pub names: CaseStyle,
+    pub arguments: CaseStyle,
+}
+
+impl Default for DirectiveCase {
+    fn default() -> Self {
+        Self {
+            names: CaseStyle::Upper,
+            arguments: CaseStyle::Preserve,
+        }
+    }
+}
+
+fn push_with_case(out: &mut String, text: &str, style: CaseStyle) {
+    match style {
+        CaseStyle::Lower => out.extend(text.chars().map(|c| c.to_ascii_lowercase())),
+        CaseStyle::Upper => out.extend(text.chars().map(|c| c.to_ascii_uppercase())),
+        CaseStyle::Pascal | CaseStyle::Preserve => out.push_str(text),
+    }
+}
+
+fn format_compiler_directive(tok: &mut Token, case: &DirectiveCase) {
+    let content = tok.get_content();
+
+    let Some(stripped) = content
+        .strip_prefix("{$")
+        .or_else(|| content.strip_prefix("(*$"))
+    else {
+        return;
+    };
+
+    enum State {
+        Before,
+        AfterPlusMinus,
+        AfterDigit,
+        AfterComma,
+        AfterLetter,
+        AfterWord,
+    }
+    let mut is_switch = false;
+    let mut state = State::Before;
+    let mut directive_len = 0;
+    for b in stripped.bytes() {
+        match (state, b) {
+            (State::Before | State::AfterComma, b'a'..=b'z' | b'A'..=b'Z') => {
+                state = State::AfterLetter;
+            }
+
+            (State::AfterLetter, b'+' | b'-') => {
+                state = State::AfterPlusMinus;
+                is_switch = true;
+            }
+
+            (State::AfterPlusMinus | State::AfterDigit, b',') => {
+                state = State::AfterComma;
+            }
+
+            (State::AfterLetter | State::AfterDigit, b'0'..=b'9') => {
+                state = State::AfterDigit;
+                is_switch = true;
+            }
+
+            (
+                State::AfterLetter | State::AfterWord,
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_',
+            ) if !is_switch => {
+                state = State::AfterWord;
+            }
+
+            // A comma after a letter is only valid in the `AfterWord` case
+            (State::AfterLetter, b',') => return,
+
+            // In a switch directive, commas and letters must be followed by something else
+            (State::AfterComma | State::AfterLetter, _) => return,
+
+            _ => break,
+        };
+
+        directive_len += 1;
+    }
+
+    let directive = &stripped[..directive_len];
+    if directive.is_empty() {
+        return;
+    }
+
+    let prefix = &content[..content.len() - stripped.len()];
+    let rest = &stripped[directive.len()..];
+
+    let mut str = String::with_capacity(content.len());
+    str.push_str(prefix);
+    push_with_case(&mut str, directive, case.names);
+    for (i, part) in rest.split('\'').enumerate() {
+        if i > 0 {
+            str.push('\'');
+        }
+        match i % 2 {
+            // Outside of a quoted string
+            0 => push_with_case(&mut str, part, case.arguments),
+            _ => str.push_str(part),
+        }
+    }
+
+    if str != content {
+        tok.set_content(str);
+    }
+}
+
+impl LogicalLineFileFormatter for DirectiveCase {
+    fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, _input: &[LogicalLine]) {
+        for (tok, _) in formatted_tokens.tokens_mut() {
+            let Ok(tok) = tok else { continue };
+            if matches!(
+                tok.get_token_type(),
+                TokenType::CompilerDirective | TokenType::ConditionalDirective(_)
+            ) {
+                format_compiler_directive(tok, self)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatter_with(names: CaseStyle, arguments: CaseStyle) -> Formatter {
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .token_ignorer(FormattingToggler {})
+            .file_formatter(DirectiveCase { names, arguments })
+            .reconstructor(default_test_reconstructor())
+            .build()
+    }
+
+    fn formatter() -> Formatter {
+        formatter_with(CaseStyle::Upper, CaseStyle::Preserve)
+    }
+
+    formatter_test_group!(
+        compiler_directives,
+        brace_style = {
+            "{$define foo}",
+            "{$DEFINE foo}",
+        },
+        paren_star_style = {
+            "(*$define foo*)",
+            "(*$DEFINE foo*)",
+        },
+        already_uppercase = {
+            "{$DEFINE foo}",
+            "{$DEFINE foo}",
+        },
+        nested_style = {
+            "(*$message '}'*){$message '*)'}",
+            "(*$MESSAGE '}'*){$MESSAGE '*)'}",
+        },
+        invalid_space_at_start = {
+            "(*$ define foo*)",
+            "(*$ define foo*)",
+        },
+        no_space_after = {
+            "{$message''}",
+            "{$MESSAGE''}",
+        },
+        switch_directives = {
+            "{$o+}{$r-}{$z2}{$a16}",
+            "{$O+}{$R-}{$Z2}{$A16}",
+        },
+        batched_switch_directives = {
+            "{$o+,r-,b+,a+,a1,a2,a4,a8,a16,z1,z2,z4}{$a1,b+}",
+            "{$O+,R-,B+,A+,A1,A2,A4,A8,A16,Z1,Z2,Z4}{$A1,B+}",
+        },
+        fake_batched_directives = {
+            "{$if,comment}",
+            "{$IF,comment}",
+        },
+        unknown_directive_names = {
+            "{$asdf}{$fdsa}{$as_df}{$as09df}{$asdf09}{$zyxw}",
+            "{$ASDF}{$FDSA}{$AS_DF}{$AS09DF}{$ASDF09}{$ZYXW}",
+        },
+        invalid_directive_names_ignored = {
+            "{$0asdf}{$,a}{$a,b}",
+            "{$0asdf}{$,a}{$a,b}",
+        },
+        incomplete_switch_directive_ignored = {
+            "{$a}{$a+,b}{$a+,}",
+            "{$a}{$a+,b}{$a+,}",
+        },
+        unusual_word_breaks = {
+            "{$a1a}{$a+!a}{$aa-a}{$a_b=a}",
+            "{$A1a}{$A+!a}{$AA-a}{$A_B=a}",
+        },
+        ignored_tokens = {
+            "{$r+}{pasfmt off}{$r+}",
+            "{$R+}{pasfmt off}{$r+}",
+        },
+        trailing_whitespace_ignored = {
+            "{$DEFINE foo  }",
+            "{$DEFINE foo  }",
+        },
+    );
+
+    formatter_test_group!(
+        conditional_directives,
+        simple_expressions = {
+            "{$if foo}{$elseif bar}{$endif}",
+            "{$IF foo}{$ELSEIF bar}{$ENDIF}",
+        },
+        complex_expressions_not_formatted = {
+            "{$if  (foo>bar  ) and true   }",
+            "{$IF  (foo>bar  ) and true   }",
+        },
+        all_directives = {
+            "{$ifdef FOo}{$ifndef fOo}{$if fOo}{$elseif fOo}{$else}{$endif}{$ifend}",
+            "{$IFDEF FOo}{$IFNDEF fOo}{$IF fOo}{$ELSEIF fOo}{$ELSE}{$ENDIF}{$IFEND}",
+        },
+        nested_directives_not_formatted = {
+            "{$if {$include foo.inc}}",
+            "{$IF {$include foo.inc}}",
+        },
+        tricky_quoting = {
+            "{$if foo = '}'}{$if bar}",
+            "{$IF foo = '}'}{$IF bar}",
+        },
+    );
+
+    mod lower {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(CaseStyle::Lower, CaseStyle::Preserve)
+        }
+
+        formatter_test_group!(
+            tests,
+            names = {"{$IFDEF Foo}{$Hints ON}", "{$ifdef Foo}{$hints ON}"},
+            switch_directives = {"{$O+,R-}{$Z2}", "{$o+,r-}{$z2}"},
+        );
+    }
+
+    mod preserve {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(CaseStyle::Preserve, CaseStyle::Preserve)
+        }
+
+        formatter_test_group!(
+            tests,
+            names = {"{$IfDef Foo}{$hints ON}", "{$IfDef Foo}{$hints ON}"},
+        );
+    }
+
+    mod arguments {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(CaseStyle::Upper, CaseStyle::Upper)
+        }
+
+        formatter_test_group!(
+            tests,
+            symbols = {"{$ifdef debug}{$if defined(foo)}", "{$IFDEF DEBUG}{$IF DEFINED(FOO)}"},
+            switch_values = {"{$hints off}", "{$HINTS OFF}"},
+            strings_are_preserved = {
+                "{$message 'it''s a message' warn}",
+                "{$MESSAGE 'it''s a message' WARN}",
+            },
+            paren_star_style = {"(*$define foo*)", "(*$DEFINE FOO*)"},
+            invalid_directive_names_ignored = {"{$0asdf}", "{$0asdf}"},
+        );
+    }
+}
@@ -6,6 +6,13 @@ use log::warn;
 enum FormattingToggle {
     On,
     Off,
+    /// Leave the next logical line, and its child lines, unformatted. If the
+    /// line is a section keyword (e.g. `uses` or `var`), the section's clause
+    /// or declarations are left unformatted too.
+    Skip,
+    /// Don't introduce new line breaks in the next logical line. Its existing
+    /// line breaks may still be removed.
+    NoWrap,
 }
 
 fn count_prefix_bytes(input: &str, f: impl Fn(&u8) -> bool) -> usize {
@@ -41,8 +48,14 @@ fn parse_pasfmt_toggle(input: &str) -> Option<FormattingToggle> {
         Some(FormattingToggle::On)
     } else if word.eq_ignore_ascii_case("off") {
         Some(FormattingToggle::Off)
+    } else if word.eq_ignore_ascii_case("skip") {
+        Some(FormattingToggle::Skip)
+    } else if word.eq_ignore_ascii_case("nowrap") {
+        Some(FormattingToggle::NoWrap)
     } else {
-        warn!("pasfmt directive comment found but '{word}' is neither 'on' nor 'off'");
+        warn!(
+            "pasfmt directive comment found but '{word}' is not one of 'on', 'off', 'skip', or 'nowrap'"
+        );
         None
     }
 }
@@ -63,22 +76,218 @@ fn parse_toggle(content: &str) -> Option<FormattingToggle> {
     parse_pasfmt_directive_comment_contents(content)
 }
 
+/// A `key=value` pair from a `// pasfmt: key=value, ...` comment.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InlineSetting {
+    pub key: String,
+    pub value: String,
+}
+
+fn parse_inline_settings_comment(content: &str) -> Option<Vec<InlineSetting>> {
+    let content = if let Some(content) = content.strip_prefix("//") {
+        content
+    } else if let Some(content) = content.strip_prefix("(*") {
+        content.strip_suffix("*)").unwrap_or(content)
+    } else {
+        let content = content.strip_prefix('{')?;
+        content.strip_suffix('}').unwrap_or(content)
+    };
+
+    let content = strip_prefix_bytes(content, u8::is_ascii_whitespace);
+    let content = strip_prefix_icase(content, "pasfmt")?;
+    let content = strip_prefix_bytes(content, u8::is_ascii_whitespace);
+    let content = content.strip_prefix(':')?;
+
+    let settings = content
+        .split(',')
+        .map(str::trim)
+        .filter(|setting| !setting.is_empty())
+        .filter_map(|setting| match setting.split_once('=') {
+            Some((key, value)) => Some(InlineSetting {
+                key: key.trim().to_owned(),
+                value: value.trim().to_owned(),
+            }),
+            None => {
+                warn!(
+                    "pasfmt settings comment found but '{setting}' is not of the form 'key=value'"
+                );
+                None
+            }
+        })
+        .collect();
+    Some(settings)
+}
+
+/// The comments before the first non-comment token of the input.
+///
+/// Only this header is scanned, so the rest of the input is not lexed.
+fn leading_comments(mut input: &str) -> impl Iterator<Item = &str> {
+    std::iter::from_fn(move || {
+        input = input.trim_start();
+        let len = if input.starts_with("//") {
+            input.find(['\r', '\n']).unwrap_or(input.len())
+        } else if input.starts_with("(*") && !input.starts_with("(*$") {
+            input.find("*)").map_or(input.len(), |end| end + 2)
+        } else if input.starts_with('{') && !input.starts_with("{$") {
+            input.find('}').map_or(input.len(), |end| end + 1)
+        } else {
+            return None;
+        };
+        let (comment, rest) = input.split_at(len);
+        input = rest;
+        Some(comment)
+    })
+}
+
+/// Finds the settings in `// pasfmt: key=value, ...` comments that come before
+/// the first non-comment token of a file.
+pub fn find_inline_settings(input: &str) -> Vec<InlineSetting> {
+    leading_comments(input)
+        .filter_map(parse_inline_settings_comment)
+        .flatten()
+        .collect()
+}
+
+/// The index of the logical line containing the first non-comment token after
+/// `token_index`, if it directly follows a run of comments.
+fn get_next_line(
+    tokens: &[Token],
+    token_lines: &[Option<usize>],
+    token_index: usize,
+) -> Option<usize> {
+    tokens
+        .iter()
+        .enumerate()
+        .skip(token_index + 1)
+        .find(|(_, token)| !matches!(token.get_token_type(), TokenType::Comment(_)))
+        .filter(|(_, token)| token.get_token_type() != TokenType::Eof)
+        .and_then(|(index, _)| token_lines[index])
+}
+
+/// Whether a logical line is preceded by a `pasfmt nowrap` comment, in which
+/// case no new line breaks should be introduced in it.
+pub(crate) fn is_nowrap_line(tokens: &FormattedTokens, line: &LogicalLine) -> bool {
+    let Some(&first_token) = line.get_tokens().first() else {
+        return false;
+    };
+    (0..first_token)
+        .rev()
+        .map_while(|index| tokens.get_token(index))
+        .take_while(|(token, _)| matches!(token.get_token_type(), TokenType::Comment(_)))
+        .any(|(token, _)| {
+            matches!(
+                parse_toggle(token.get_content()),
+                Some(FormattingToggle::NoWrap)
+            )
+        })
+}
+
+/// Whether the token at `token_index` is left unformatted by the toggle
+/// comments before it, i.e. a `pasfmt off` without a following `pasfmt on`,
+/// or a `pasfmt skip` directly before it.
+pub(crate) fn is_toggled_off(tokens: &[RawToken], token_index: usize) -> bool {
+    let mut ignored = false;
+    let mut skipped = false;
+    for token in &tokens[..token_index] {
+        match token.get_token_type() {
+            RawTokenType::Comment(_) => match parse_toggle(token.get_content()) {
+                Some(FormattingToggle::Off) => ignored = true,
+                Some(FormattingToggle::On) => ignored = false,
+                Some(FormattingToggle::Skip) => skipped = true,
+                Some(FormattingToggle::NoWrap) | None => {}
+            },
+            _ => skipped = false,
+        }
+    }
+    ignored || skipped
+}
+
+/// Whether the line is only a keyword which starts a section of import clauses
+/// or declarations, e.g. `uses` or `var`.
+fn is_section_keyword_line(tokens: &[Token], line: &LogicalLine) -> bool {
+    let &[token_index] = line.get_tokens().as_slice() else {
+        return false;
+    };
+    matches!(
+        tokens[token_index].get_token_type(),
+        TokenType::Keyword(
+            KeywordKind::Uses
+                | KeywordKind::Contains
+                | KeywordKind::Requires
+                | KeywordKind::Const(_)
+                | KeywordKind::Var(_)
+                | KeywordKind::ThreadVar
+                | KeywordKind::Type
+                | KeywordKind::ResourceString
+                | KeywordKind::Label
+        )
+    )
+}
+
+/// The lines left unformatted by a `pasfmt skip` before `line_index`, not
+/// including their child lines.
+fn get_skipped_lines(tokens: &[Token], lines: &[LogicalLine], line_index: usize) -> Vec<usize> {
+    let line = &lines[line_index];
+    let mut skipped_lines = vec![line_index];
+    if is_section_keyword_line(tokens, line) {
+        skipped_lines.extend(
+            lines
+                .iter()
+                .enumerate()
+                .skip(line_index + 1)
+                .filter(|(_, section_line)| section_line.get_parent().is_none())
+                .take_while(|(_, section_line)| section_line.get_level() > line.get_level())
+                .map(|(index, _)| index),
+        );
+    }
+    skipped_lines
+}
+
+/// Whether the token is a `pasfmt` toggle comment.
+pub(crate) fn is_toggle_comment(token: &RawToken) -> bool {
+    matches!(token.get_token_type(), RawTokenType::Comment(_))
+        && parse_toggle(token.get_content()).is_some()
+}
+
 pub struct FormattingToggler {}
 impl TokenIgnorer for FormattingToggler {
     fn ignore_tokens(&self, input: (&[Token], &[LogicalLine]), token_marker: &mut TokenMarker) {
+        let (tokens, lines) = input;
+        let mut token_lines = vec![None; tokens.len()];
+        for (line_index, line) in lines.iter().enumerate() {
+            for &token_index in line.get_tokens() {
+                token_lines[token_index] = Some(line_index);
+            }
+        }
+
         let mut ignored = false;
-        for (i, token) in input.0.iter().enumerate() {
+        let mut skipped_lines = vec![];
+        for (i, token) in tokens.iter().enumerate() {
             let mut on_toggle_comment = false;
             if let TokenType::Comment(_) = token.get_token_type() {
                 on_toggle_comment = true;
                 match parse_toggle(token.get_content()) {
                     Some(FormattingToggle::Off) => ignored = true,
                     Some(FormattingToggle::On) => ignored = false,
+                    Some(FormattingToggle::Skip) => {
+                        skipped_lines = get_next_line(tokens, &token_lines, i)
+                            .map(|line_index| get_skipped_lines(tokens, lines, line_index))
+                            .unwrap_or_default()
+                    }
+                    Some(FormattingToggle::NoWrap) => {}
                     None => on_toggle_comment = false,
                 };
             }
 
-            if ignored | on_toggle_comment {
+            let in_skipped_line = !skipped_lines.is_empty()
+                && std::iter::successors(token_lines[i], |&line_index| {
+                    lines[line_index]
+                        .get_parent()
+                        .map(|parent| parent.line_index)
+                })
+                .any(|line_index| skipped_lines.contains(&line_index));
+
+            if ignored | on_toggle_comment | in_skipped_line {
                 token_marker.mark(i);
             }
         }
@@ -117,6 +326,59 @@ mod tests {
             .build()
     }
 
+    fn inline_settings(input: &str) -> Vec<(String, String)> {
+        find_inline_settings(input)
+            .into_iter()
+            .map(|setting| (setting.key, setting.value))
+            .collect()
+    }
+
+    fn settings(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|&(key, value)| (key.to_owned(), value.to_owned()))
+            .collect()
+    }
+
+    #[test]
+    fn inline_settings_in_header() {
+        assert_eq!(
+            inline_settings("// pasfmt: wrap_column=80, begin_style = always_wrap\nunit A;"),
+            settings(&[("wrap_column", "80"), ("begin_style", "always_wrap")])
+        );
+        assert_eq!(
+            inline_settings(
+                "{ Generated }\n{ PASFMT: a=1 }\n(* pasfmt:b=2 *)\n// pasfmt off\nunit A;"
+            ),
+            settings(&[("a", "1"), ("b", "2")])
+        );
+    }
+
+    #[test]
+    fn inline_settings_after_code_are_ignored() {
+        assert_eq!(inline_settings("unit A;\n// pasfmt: a=1"), settings(&[]));
+    }
+
+    #[test]
+    fn inline_settings_after_directives_are_ignored() {
+        assert_eq!(
+            inline_settings("{$IFDEF A}\n// pasfmt: a=1\n{$ENDIF}"),
+            settings(&[])
+        );
+        assert_eq!(
+            inline_settings("(*$HINTS OFF*)\n(* pasfmt: a=1 *)"),
+            settings(&[])
+        );
+    }
+
+    #[test]
+    fn malformed_inline_settings_are_skipped() {
+        assert_eq!(
+            inline_settings("// pasfmt: a, b=2,\n// pasfmt c=3\nunit A;"),
+            settings(&[("b", "2")])
+        );
+    }
+
     formatter_test_group!(
         formatting_toggle,
         not_disabled = {"Foo(Bar + Baz)", " Foo ( Bar  +  Baz )"},
@@ -369,6 +631,76 @@ mod tests {
               Foo(Bar);
               "
             }
-        }
+        },
+        skip_next_line = {
+            indoc! {
+              "
+              Foo(Bar);
+              // pasfmt skip
+              Foo(Bar);
+              Foo(Bar);
+              "
+            },
+            indoc! {
+              "
+               Foo ( Bar ) ;
+              // pasfmt skip
+              Foo(Bar);
+               Foo ( Bar ) ;
+              "
+            },
+        },
+        skip_includes_child_lines = {
+            indoc! {
+              "
+              // pasfmt skip
+              Foo(procedure begin Bar; end);
+              Foo(Bar);
+              "
+            },
+            indoc! {
+              "
+              // pasfmt skip
+              Foo(procedure begin Bar; end);
+               Foo ( Bar ) ;
+              "
+            },
+        },
+        skip_includes_section = {
+            indoc! {
+              "
+              // pasfmt skip
+              const
+                A   = 1;
+                Bcd = 2;
+              var
+                C: Integer;
+              "
+            },
+            indoc! {
+              "
+              // pasfmt skip
+              const
+                A   = 1;
+                Bcd = 2;
+               var
+                 C :  Integer ;
+              "
+            },
+        },
+        nowrap_is_formatted = {
+            indoc! {
+              "
+              // pasfmt nowrap
+              Foo(Bar);
+              "
+            },
+            indoc! {
+              "
+              // pasfmt nowrap
+               Foo ( Bar ) ;
+              "
+            },
+        },
     );
 }
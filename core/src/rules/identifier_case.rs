@@ -0,0 +1,429 @@
+use fxhash::FxHashMap;
+
+use crate::lang::OperatorKind as OK;
+use crate::lang::TokenType as TT;
+use crate::prelude::*;
+
+/// Rewrites each identifier to the casing of its first declaration in the
+/// file.
+///
+/// Declarations are found in `Declaration`, `RoutineHeader` and
+/// `InlineDeclaration` lines, and in the parameter lists of routines. The
+/// implicit `Result` variable is cased as `Result` in the bodies of functions,
+/// unless it is declared otherwise.
+pub struct IdentifierCase {}
+
+fn first_keyword(formatted_tokens: &FormattedTokens, line: &LogicalLine) -> Option<KeywordKind> {
+    match line
+        .get_tokens()
+        .first()
+        .and_then(|&token_index| formatted_tokens.get_token_type_for_index(token_index))
+    {
+        Some(TT::Keyword(keyword)) => Some(keyword),
+        _ => None,
+    }
+}
+
+/// The first and last token of the `begin`...`end` (or `asm`...`end`) body of
+/// each function and operator implementation.
+fn function_bodies(
+    formatted_tokens: &FormattedTokens,
+    input: &[LogicalLine],
+) -> Vec<(usize, usize)> {
+    let lines: Vec<&LogicalLine> = input
+        .iter()
+        .filter(|line| line.get_parent().is_none() && !line.get_tokens().is_empty())
+        .collect();
+
+    let mut bodies = vec![];
+    for (index, header) in lines.iter().enumerate() {
+        let is_function = header.get_line_type() == LogicalLineType::RoutineHeader
+            && header.get_tokens().iter().any(|&token_index| {
+                matches!(
+                    formatted_tokens.get_token_type_for_index(token_index),
+                    Some(TT::Keyword(KeywordKind::Function | KeywordKind::Operator))
+                )
+            });
+        if !is_function {
+            continue;
+        }
+
+        // The body follows the declarations of the routine, which are either
+        // nested deeper, or are sections like `var` at the routine's level.
+        let level = header.get_level();
+        let mut rest = lines[index + 1..].iter().skip_while(|line| {
+            line.get_level() > level
+                || (line.get_level() == level
+                    && matches!(
+                        first_keyword(formatted_tokens, line),
+                        Some(
+                            KeywordKind::Var(_)
+                                | KeywordKind::Const(_)
+                                | KeywordKind::Type
+                                | KeywordKind::Label
+                        )
+                    ))
+        });
+        let Some(begin) = rest.next().filter(|line| {
+            line.get_level() == level
+                && matches!(
+                    first_keyword(formatted_tokens, line),
+                    Some(KeywordKind::Begin | KeywordKind::Asm)
+                )
+        }) else {
+            continue;
+        };
+        let Some(end) = rest.find(|line| {
+            line.get_level() == level
+                && first_keyword(formatted_tokens, line) == Some(KeywordKind::End)
+        }) else {
+            continue;
+        };
+        if let (Some(&first), Some(&last)) = (begin.get_tokens().first(), end.get_tokens().last()) {
+            bodies.push((first, last));
+        }
+    }
+    bodies
+}
+
+struct DeclarationFinder<'a, 'b> {
+    tokens: &'a FormattedTokens<'b>,
+    declarations: Vec<usize>,
+}
+
+impl DeclarationFinder<'_, '_> {
+    fn token_type(&self, token_index: usize) -> Option<TokenType> {
+        self.tokens.get_token_type_for_index(token_index)
+    }
+
+    /// Adds the identifiers before the first of `terminators` that isn't
+    /// nested in parentheses or brackets.
+    fn add_names_until(&mut self, line_tokens: &[usize], terminators: &[TokenType]) {
+        let mut depth = 0;
+        for &token_index in line_tokens {
+            match self.token_type(token_index) {
+                Some(TT::Op(OK::LParen | OK::LBrack)) => depth += 1,
+                Some(TT::Op(OK::RParen | OK::RBrack)) => depth -= 1,
+                Some(TT::Identifier) if depth == 0 => self.declarations.push(token_index),
+                Some(token_type) if depth == 0 && terminators.contains(&token_type) => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds the name of the routine declared in a routine header, i.e. `Bar`
+    /// in `procedure TFoo<T>.Bar(...)`.
+    fn add_routine_name(&mut self, line_tokens: &[usize]) {
+        let mut depth = 0;
+        let mut name = None;
+        for &token_index in line_tokens {
+            match self.token_type(token_index) {
+                Some(TT::Op(OK::LessThan(_))) => depth += 1,
+                Some(TT::Op(OK::GreaterThan(_))) => depth -= 1,
+                Some(TT::Identifier) if depth == 0 => name = Some(token_index),
+                Some(TT::Op(OK::LParen | OK::Colon | OK::Semicolon | OK::Equal(_))) => break,
+                _ => {}
+            }
+        }
+        self.declarations.extend(name);
+    }
+
+    /// Adds the parameter names of each parameter list in the line, i.e. `A`
+    /// and `B` in `procedure Foo(const A: Integer; var B);`.
+    fn add_parameter_names(&mut self, line_tokens: &[usize]) {
+        let mut routine_seen = false;
+        let mut tokens = line_tokens.iter().copied();
+        while let Some(token_index) = tokens.next() {
+            match self.token_type(token_index) {
+                Some(TT::Keyword(
+                    KeywordKind::Procedure
+                    | KeywordKind::Function
+                    | KeywordKind::Constructor
+                    | KeywordKind::Destructor
+                    | KeywordKind::Operator,
+                )) => routine_seen = true,
+                Some(TT::Op(OK::Colon | OK::Semicolon)) => routine_seen = false,
+                Some(TT::Op(OK::LParen)) if routine_seen => {
+                    routine_seen = false;
+                    self.add_parameter_list(&mut tokens);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn add_parameter_list(&mut self, tokens: &mut impl Iterator<Item = usize>) {
+        let mut depth = 0;
+        let mut in_names = true;
+        for token_index in tokens {
+            match self.token_type(token_index) {
+                Some(TT::Op(OK::LParen | OK::LBrack)) => depth += 1,
+                Some(TT::Op(OK::RParen)) if depth == 0 => return,
+                Some(TT::Op(OK::RParen | OK::RBrack)) => depth -= 1,
+                Some(TT::Op(OK::Colon)) if depth == 0 => in_names = false,
+                Some(TT::Op(OK::Semicolon)) if depth == 0 => in_names = true,
+                Some(TT::Identifier) if depth == 0 && in_names => {
+                    self.declarations.push(token_index)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn add_line(&mut self, line: &LogicalLine) {
+        let line_tokens = line.get_tokens();
+        match line.get_line_type() {
+            LogicalLineType::Declaration => self.add_names_until(
+                line_tokens,
+                &[TT::Op(OK::Colon), TT::Op(OK::Equal(EqKind::Decl))],
+            ),
+            LogicalLineType::InlineDeclaration => self.add_names_until(
+                line_tokens,
+                &[
+                    TT::Op(OK::Colon),
+                    TT::Op(OK::Assign),
+                    TT::Op(OK::Equal(EqKind::Decl)),
+                ],
+            ),
+            LogicalLineType::RoutineHeader => self.add_routine_name(line_tokens),
+            _ => {}
+        }
+        self.add_parameter_names(line_tokens);
+    }
+}
+
+impl LogicalLineFileFormatter for IdentifierCase {
+    fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, input: &[LogicalLine]) {
+        let mut finder = DeclarationFinder {
+            tokens: formatted_tokens,
+            declarations: vec![],
+        };
+        for line in input {
+            finder.add_line(line);
+        }
+        let mut declarations = finder.declarations;
+        declarations.sort_unstable();
+
+        let mut casing: FxHashMap<String, String> = FxHashMap::default();
+        for token_index in declarations {
+            if let Some((token, _)) = formatted_tokens.get_token(token_index) {
+                let name = token.get_content();
+                casing
+                    .entry(name.to_lowercase())
+                    .or_insert_with(|| name.to_owned());
+            }
+        }
+        let bodies = if casing.contains_key("result") {
+            vec![]
+        } else {
+            function_bodies(formatted_tokens, input)
+        };
+        if casing.is_empty() && bodies.is_empty() {
+            return;
+        }
+
+        let result = "Result".to_owned();
+        for (token_index, (tok, _)) in formatted_tokens.tokens_mut().enumerate() {
+            let Ok(tok) = tok else { continue };
+            if tok.get_token_type() != TT::Identifier {
+                continue;
+            }
+            let lowercase = tok.get_content().to_lowercase();
+            let name = casing.get(&lowercase).or_else(|| {
+                (lowercase == "result"
+                    && bodies
+                        .iter()
+                        .any(|&(first, last)| (first..=last).contains(&token_index)))
+                .then_some(&result)
+            });
+            if let Some(name) = name
+                && name != tok.get_content()
+            {
+                tok.set_content(name.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn formatter() -> Formatter {
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .token_consolidator(DistinguishGenericTypeParamsConsolidator {})
+            .token_ignorer(FormattingToggler {})
+            .file_formatter(IdentifierCase {})
+            .reconstructor(default_test_reconstructor())
+            .build()
+    }
+
+    formatter_test_group!(
+        tests,
+        var_declaration = {
+            "var FooBar: Integer; begin foobar := FOOBAR; end;",
+            "var FooBar: Integer; begin FooBar := FooBar; end;",
+        },
+        multiple_names = {
+            "var A, Bb: Integer; begin a := bB; end;",
+            "var A, Bb: Integer; begin A := Bb; end;",
+        },
+        type_names_are_declared = {
+            "type TFoo<T> = class end; var f: tfoo<t>;",
+            "type TFoo<T> = class end; var f: TFoo<T>;",
+        },
+        types_of_declarations_are_not_declared = {
+            "var a: tstringlist; b: TStringList;",
+            "var a: tstringlist; b: TStringList;",
+        },
+        const_declaration = {
+            "const MaxCount = 10; begin x := MAXCOUNT; end;",
+            "const MaxCount = 10; begin x := MaxCount; end;",
+        },
+        first_declaration_wins = {
+            "var Value: Integer; var VALUE: Integer;",
+            "var Value: Integer; var Value: Integer;",
+        },
+        routine_name = {
+            indoc! {"
+                procedure TFoo.SetValue;
+                begin
+                  setvalue;
+                end;
+            "},
+            indoc! {"
+                procedure TFoo.SetValue;
+                begin
+                  SetValue;
+                end;
+            "},
+        },
+        generic_routine_name = {
+            "function TFoo<T>.GetItem<U>: T; begin getitem; end;",
+            "function TFoo<T>.GetItem<U>: T; begin GetItem; end;",
+        },
+        parameters = {
+            indoc! {"
+                procedure Foo(const AValue: Integer; var B, C: string = 'x'; [Ref] D: Integer);
+                begin
+                  avalue := b + c + d + ref;
+                end;
+            "},
+            indoc! {"
+                procedure Foo(const AValue: Integer; var B, C: string = 'x'; [Ref] D: Integer);
+                begin
+                  AValue := B + C + D + ref;
+                end;
+            "},
+        },
+        anonymous_routine_parameters = {
+            "f(procedure(AItem: Integer) begin g(aitem); end);",
+            "f(procedure(AItem: Integer) begin g(AItem); end);",
+        },
+        procedural_type_parameters = {
+            "type TProc = reference to procedure(AItem: Integer); begin x := aitem; tproc; end;",
+            "type TProc = reference to procedure(AItem: Integer); begin x := AItem; TProc; end;",
+        },
+        inline_declarations = {
+            indoc! {"
+                begin
+                  var LTemp := 1;
+                  const LConst: Integer = 2;
+                  x := ltemp + lconst;
+                end;
+            "},
+            indoc! {"
+                begin
+                  var LTemp := 1;
+                  const LConst: Integer = 2;
+                  x := LTemp + LConst;
+                end;
+            "},
+        },
+        implicit_result = {
+            indoc! {"
+                function Foo: Integer;
+                var
+                  X: Integer;
+                begin
+                  result := X;
+                  if RESULT > 0 then begin
+                    Exit(result);
+                  end;
+                end;
+
+                procedure Bar;
+                begin
+                  result := 1;
+                end;
+            "},
+            indoc! {"
+                function Foo: Integer;
+                var
+                  X: Integer;
+                begin
+                  Result := X;
+                  if Result > 0 then begin
+                    Exit(Result);
+                  end;
+                end;
+
+                procedure Bar;
+                begin
+                  result := 1;
+                end;
+            "},
+        },
+        declared_result = {
+            "function Foo: Integer; var RESULT: Integer; begin result := 1; end;",
+            "function Foo: Integer; var RESULT: Integer; begin RESULT := 1; end;",
+        },
+        strings_and_comments_are_not_changed = {
+            "var Foo: Integer; begin x := 'foo'; // foo\nend;",
+            "var Foo: Integer; begin x := 'foo'; // foo\nend;",
+        },
+        ignored_tokens = {
+            indoc! {"
+                var Foo: Integer;
+                begin
+                  // pasfmt off
+                  foo := 1;
+                  // pasfmt on
+                  foo := 1;
+                end;
+            "},
+            indoc! {"
+                var Foo: Integer;
+                begin
+                  // pasfmt off
+                  foo := 1;
+                  // pasfmt on
+                  Foo := 1;
+                end;
+            "},
+        },
+        ignored_declarations = {
+            indoc! {"
+                // pasfmt off
+                var FOO: Integer;
+                // pasfmt on
+                var Foo: Integer;
+                begin
+                  foo := 1;
+                end;
+            "},
+            indoc! {"
+                // pasfmt off
+                var FOO: Integer;
+                // pasfmt on
+                var Foo: Integer;
+                begin
+                  Foo := 1;
+                end;
+            "},
+        },
+    );
+}
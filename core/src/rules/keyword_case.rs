@@ -0,0 +1,239 @@
+use crate::prelude::*;
+
+/// The casing applied to a word.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CaseStyle {
+    #[default]
+    Lower,
+    Upper,
+    /// Each word within the text is capitalised, e.g. `StdCall`.
+    Pascal,
+    /// The text is left as it is.
+    Preserve,
+}
+
+/// Sets the casing of keywords, with separate [`CaseStyle`]s for reserved
+/// words and for impure keywords (see [`KeywordKind::is_impure`]).
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KeywordCase {
+    pub reserved_words: CaseStyle,
+    pub impure_keywords: CaseStyle,
+}
+
+fn pascal_case(kind: KeywordKind) -> String {
+    // Keywords made of several words are spelled out, as the variant names
+    // don't always capitalise each word (e.g. `Downto`).
+    let compound = match kind {
+        KeywordKind::Cdecl => "CDecl",
+        KeywordKind::DispId => "DispId",
+        KeywordKind::DispInterface => "DispInterface",
+        KeywordKind::Downto => "DownTo",
+        KeywordKind::NoDefault => "NoDefault",
+        KeywordKind::NoReturn => "NoReturn",
+        KeywordKind::ReadOnly => "ReadOnly",
+        KeywordKind::ResourceString => "ResourceString",
+        KeywordKind::SafeCall => "SafeCall",
+        KeywordKind::StdCall => "StdCall",
+        KeywordKind::ThreadVar => "ThreadVar",
+        KeywordKind::VarArgs => "VarArgs",
+        KeywordKind::WinApi => "WinApi",
+        KeywordKind::WriteOnly => "WriteOnly",
+        _ => {
+            // The variant names of single-word keywords are their PascalCase
+            // spellings.
+            let name = format!("{kind:?}");
+            return match name.split_once('(') {
+                Some((name, _)) => name.to_owned(),
+                None => name,
+            };
+        }
+    };
+    compound.to_owned()
+}
+
+impl LogicalLineFileFormatter for KeywordCase {
+    fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, _input: &[LogicalLine]) {
+        for (tok, _) in formatted_tokens.tokens_mut() {
+            let Ok(tok) = tok else { continue };
+            let TokenType::Keyword(kind) = tok.get_token_type() else {
+                continue;
+            };
+            let style = match kind.is_impure() {
+                true => self.impure_keywords,
+                false => self.reserved_words,
+            };
+
+            let content = tok.get_content();
+            let new_content = match style {
+                CaseStyle::Lower if content.bytes().any(|b| b.is_ascii_uppercase()) => {
+                    content.to_ascii_lowercase()
+                }
+                CaseStyle::Upper if content.bytes().any(|b| b.is_ascii_lowercase()) => {
+                    content.to_ascii_uppercase()
+                }
+                CaseStyle::Pascal => match pascal_case(kind) {
+                    name if name != content && name.eq_ignore_ascii_case(content) => name,
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            tok.set_content(new_content);
+        }
+    }
+}
+
+/// Converts keywords to lowercase.
+#[deprecated(note = "use `KeywordCase` instead")]
+pub struct LowercaseKeywords {}
+
+#[allow(deprecated)]
+impl LogicalLineFileFormatter for LowercaseKeywords {
+    fn format(&self, formatted_tokens: &mut FormattedTokens<'_>, input: &[LogicalLine]) {
+        KeywordCase {
+            reserved_words: CaseStyle::Lower,
+            impure_keywords: CaseStyle::Lower,
+        }
+        .format(formatted_tokens, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatter_with(reserved_words: CaseStyle, impure_keywords: CaseStyle) -> Formatter {
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .token_ignorer(FormattingToggler {})
+            .file_formatter(KeywordCase {
+                reserved_words,
+                impure_keywords,
+            })
+            .reconstructor(default_test_reconstructor())
+            .build()
+    }
+
+    fn formatter() -> Formatter {
+        formatter_with(CaseStyle::Lower, CaseStyle::Lower)
+    }
+
+    formatter_test_group!(
+        tests,
+        full_uppercase = {
+            "BEGIN END",
+            "begin end"
+        },
+        partial_uppercase = {
+            "begIn enD",
+            "begin end"
+        },
+        lowercase = {
+            "begin end",
+            "begin end"
+        },
+        impure_keyword_is_ignored = {
+            "ABSOLUTE := 0",
+            "ABSOLUTE := 0",
+        },
+        impure_keyword_is_formatted = {
+            "var a: b ABSOLUTE c",
+            "var a: b absolute c",
+        },
+        ignored_tokens = {
+            "{pasfmt off} BEGIN {pasfmt on} END",
+            "{pasfmt off} BEGIN {pasfmt on} end",
+        }
+    );
+
+    mod upper {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(CaseStyle::Upper, CaseStyle::Upper)
+        }
+
+        formatter_test_group!(
+            tests,
+            lowercase = {"begin end", "BEGIN END"},
+            mixed_case = {"Begin enD", "BEGIN END"},
+            impure_keyword_is_formatted = {"var a: b Absolute c", "VAR a: b ABSOLUTE c"},
+        );
+    }
+
+    mod pascal {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(CaseStyle::Pascal, CaseStyle::Pascal)
+        }
+
+        formatter_test_group!(
+            tests,
+            single_word = {"BEGIN end", "Begin End"},
+            multiple_words = {
+                "procedure Foo; STDCALL; safecall; overload;",
+                "Procedure Foo; StdCall; SafeCall; Overload;",
+            },
+            keyword_with_kind = {"const A = 1; var B: string;", "Const A = 1; Var B: String;"},
+            compound_words = {
+                "for i := 10 downto 1 do; procedure Foo; cdecl; threadvar A: B;",
+                "For i := 10 DownTo 1 Do; Procedure Foo; CDecl; ThreadVar A: B;",
+            },
+            identifier_is_ignored = {"stdcall := 0", "stdcall := 0"},
+        );
+    }
+
+    mod preserve {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(CaseStyle::Preserve, CaseStyle::Preserve)
+        }
+
+        formatter_test_group!(
+            tests,
+            mixed_case = {"BEGIN eNd", "BEGIN eNd"},
+        );
+    }
+
+    mod separate_styles {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(CaseStyle::Preserve, CaseStyle::Pascal)
+        }
+
+        formatter_test_group!(
+            tests,
+            legacy_reserved_words = {
+                "PROCEDURE Foo; OVERRIDE; BEGIN END;",
+                "PROCEDURE Foo; Override; BEGIN END;",
+            },
+            absolute = {"VAR a: b absolute c", "VAR a: b Absolute c"},
+        );
+    }
+
+    mod lowercase_keywords {
+        use super::*;
+
+        #[allow(deprecated)]
+        fn formatter() -> Formatter {
+            Formatter::builder()
+                .lexer(DelphiLexer {})
+                .parser(DelphiLogicalLineParser {})
+                .token_ignorer(FormattingToggler {})
+                .file_formatter(LowercaseKeywords {})
+                .reconstructor(default_test_reconstructor())
+                .build()
+        }
+
+        formatter_test_group!(
+            tests,
+            all_keywords = {
+                "PROCEDURE Foo; STDCALL; BEGIN End;",
+                "procedure Foo; stdcall; begin end;",
+            },
+        );
+    }
+}
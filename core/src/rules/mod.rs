@@ -1,21 +1,35 @@
+pub mod align_declarations;
+pub mod align_trailing_comments;
+pub mod blank_lines;
+pub mod casing_dictionary;
 pub mod comment_contents;
 pub mod conditional_directive_consolidator;
 pub mod deindent_package_directives;
+pub mod directive_case;
 pub mod eof_newline;
 pub mod formatting_toggle;
 pub mod generics_consolidator;
+pub mod identifier_case;
 pub mod ignore_asm_instructions;
-pub mod lowercase_keywords;
+pub mod keyword_case;
 pub mod optimising_line_formatter;
+pub mod sort_uses;
 pub mod token_spacing;
 
+pub use align_declarations::*;
+pub use align_trailing_comments::*;
+pub use blank_lines::*;
+pub use casing_dictionary::*;
 pub use comment_contents::*;
 pub use conditional_directive_consolidator::*;
 pub use deindent_package_directives::*;
+pub use directive_case::*;
 pub use eof_newline::*;
 pub use formatting_toggle::*;
 pub use generics_consolidator::*;
+pub use identifier_case::*;
 pub use ignore_asm_instructions::*;
-pub use lowercase_keywords::*;
+pub use keyword_case::*;
 pub use optimising_line_formatter::*;
+pub use sort_uses::*;
 pub use token_spacing::*;
@@ -147,6 +147,18 @@ impl FormattingContext {
         self.context_type
     }
 
+    pub fn continuation_delta(&self) -> u16 {
+        self.continuation_delta
+    }
+
+    pub fn starting_token(&self) -> u32 {
+        self.starting_token
+    }
+
+    pub fn ending_token(&self) -> Option<u32> {
+        self.ending_token
+    }
+
     /// Returns whether a context is to be affected by a decision
     pub fn is_active_at_token(&self, line_index: u32) -> bool {
         self.starting_token != line_index
@@ -384,10 +396,20 @@ impl<'a> SpecificContextStack<'a> {
             _ => false,
         }
     }
-    fn update_operator_precedences(&self, node: &mut FormattingNode, is_break: bool) {
+    /// Updates the contexts of operators with the given precedence or lower,
+    /// skipping those of the operators which bind more tightly.
+    fn update_operator_precedences(
+        &self,
+        node: &mut FormattingNode,
+        precedence: u8,
+        is_break: bool,
+    ) {
         self.update_last_matching_context(
             node,
-            context_matches!(CT::Precedence(_) | CT::ConditionalDirective),
+            |ctx_type| {
+                matches!(ctx_type, CT::Precedence(p) if p >= precedence)
+                    || ctx_type == CT::ConditionalDirective
+            },
             |ctx, data| {
                 // Using `ConditionalDirective` as a stop-gap
                 if matches!(ctx.context_type, CT::Precedence(_)) {
@@ -399,6 +421,9 @@ impl<'a> SpecificContextStack<'a> {
 
         if is_break {
             self.ctx_iter_indices()
+                .skip_while(
+                    |(_, context)| matches!(context.context_type, CT::Precedence(p) if p < precedence),
+                )
                 .take_while(|(_, context)| matches!(context.context_type, CT::Precedence(_)))
                 .for_each(|(index, _)| {
                     if let Some(context) = Rc::make_mut(&mut node.context_data).get_mut(index) {
@@ -430,15 +455,72 @@ impl<'a> SpecificContextStack<'a> {
             .find(|token_type| !token_type.is_comment_or_compiler_directive())
     }
 
+    /// Updates the contexts for a break before or after the `:=` or `=` of
+    /// an assignment or declaration.
+    fn update_assignment_contexts(&self, node: &mut FormattingNode, is_break: bool) {
+        let is_type_parens = self
+            .ctx_data_iter(node)
+            .next()
+            .filter(|(ctx, _)| {
+                ctx.context_type == CT::Brackets(BracketKind::Round, BracketStyle::BreakClose)
+            })
+            .is_some();
+
+        self.update_last_matching_context(
+            node,
+            context_matches!(
+                CT::Base
+                    | CT::Type
+                    | CT::TypedAssignment
+                    | CT::Assignment
+                    | CT::Subject
+                    | CT::SemicolonElem
+                    | CT::CommaElem
+            ),
+            |_, data| {
+                data.is_broken |= is_break;
+                if self.formatting_contexts.line.get_line_type() != LLT::Declaration
+                    || !is_type_parens
+                {
+                    // This is to handle the case of record arrays and
+                    // enums and the `(` after the `=`
+                    // e.g., ```
+                    // type
+                    //   AAA = (
+                    //       BB,
+                    //       CC
+                    //   );
+                    // ```
+                    data.can_break &= is_break;
+                }
+            },
+        );
+    }
+
     /// Updates all contexts to reflect the decision provided.
-    pub(super) fn update_contexts(&self, node: &mut FormattingNode, decision: RawDecision) {
+    pub(super) fn update_contexts(
+        &self,
+        node: &mut FormattingNode,
+        decision: RawDecision,
+        settings: &OptimisingLineFormatterSettings,
+    ) {
         let line_index = node.next_line_index;
 
         let is_break = decision == NL::Break;
-        let last_real_token_type = (0..line_index)
+        let prev_token_types = (0..line_index)
             .rev()
-            .filter_map(|index| self.get_token_type_from_line_index(index))
+            .filter_map(|index| self.get_token_type_from_line_index(index));
+        let last_real_token_type = prev_token_types
+            .clone()
             .find(|token_type| !token_type.is_comment_or_compiler_directive());
+        let trailing_operator_precedence = match settings.operator_position {
+            OperatorPosition::Before => None,
+            OperatorPosition::After => get_trailing_operator_precedence(prev_token_types),
+        };
+        let follows_line_ending_comment = line_index
+            .checked_sub(1)
+            .and_then(|index| self.get_token_type_from_line_index(index))
+            .is_some_and(TokenType::is_line_ending_comment);
 
         self.ctx_iter_indices()
             .skip(1)
@@ -456,6 +538,8 @@ impl<'a> SpecificContextStack<'a> {
             };
 
         let curr_token_type = self.get_token_type_from_line_index(line_index);
+        let is_assign_before = settings.assign_position == OperatorPosition::Before
+            && self.formatting_contexts.line.get_line_type() != LLT::ForLoop;
         match (last_real_token_type, curr_token_type) {
             (_, Some(TT::TextLiteral(TextLiteralKind::MultiLine))) => {
                 // There is necessarily a break within a multiline string
@@ -463,7 +547,7 @@ impl<'a> SpecificContextStack<'a> {
                 self.update_last_matching_context(node, context_matches!(_), |_, data| {
                     data.is_broken = true;
                 });
-                self.update_operator_precedences(node, true);
+                self.update_operator_precedences(node, super::HIGHEST_PRECEDENCE, true);
             }
             (_, Some(TT::Comment(CommentKind::InlineBlock | CommentKind::InlineLine))) => {
                 /*
@@ -592,45 +676,14 @@ impl<'a> SpecificContextStack<'a> {
                     data.is_broken |= is_break;
                 });
             }
+            (Some(TT::Op(OK::Assign)), _) if is_assign_before => {
+                // The break is made before the `:=` instead
+            }
+            (_, Some(TT::Op(OK::Assign))) if is_assign_before => {
+                self.update_assignment_contexts(node, is_break);
+            }
             (Some(TT::Op(OK::Assign | OK::Equal(EqKind::Decl))), _) => {
-                let is_type_parens = self
-                    .ctx_data_iter(node)
-                    .next()
-                    .filter(|(ctx, _)| {
-                        ctx.context_type
-                            == CT::Brackets(BracketKind::Round, BracketStyle::BreakClose)
-                    })
-                    .is_some();
-
-                self.update_last_matching_context(
-                    node,
-                    context_matches!(
-                        CT::Base
-                            | CT::Type
-                            | CT::TypedAssignment
-                            | CT::Assignment
-                            | CT::Subject
-                            | CT::SemicolonElem
-                            | CT::CommaElem
-                    ),
-                    |_, data| {
-                        data.is_broken |= is_break;
-                        if self.formatting_contexts.line.get_line_type() != LLT::Declaration
-                            || !is_type_parens
-                        {
-                            // This is to handle the case of record arrays and
-                            // enums and the `(` after the `=`
-                            // e.g., ```
-                            // type
-                            //   AAA = (
-                            //       BB,
-                            //       CC
-                            //   );
-                            // ```
-                            data.can_break &= is_break;
-                        }
-                    },
-                );
+                self.update_assignment_contexts(node, is_break);
             }
             (_, Some(TT::Keyword(KK::In(InKind::ForLoop) | KK::To | KK::Downto))) => {
                 self.update_last_matching_context(node, CT::ForLoop, apply_pivotal_break);
@@ -662,7 +715,7 @@ impl<'a> SpecificContextStack<'a> {
                     .map(|(ctx, data)| (ctx.context_type, data))
                 {
                     Some((CT::Precedence(_), _)) => {
-                        self.update_operator_precedences(node, is_break);
+                        self.update_operator_precedences(node, super::HIGHEST_PRECEDENCE, is_break);
                     }
                     Some((CT::MemberAccess, data)) => {
                         data.one_element_per_line.get_or_insert(is_break);
@@ -674,6 +727,18 @@ impl<'a> SpecificContextStack<'a> {
             (Some(op1), Some(op2)) if (op1, op2).get_operator_precedence().is_some() => {
                 // In the middle of a compound operator, do nothing
             }
+            (_, Some(op))
+                if settings.operator_position == OperatorPosition::After
+                    && !follows_line_ending_comment
+                    && get_positioned_operator_precedence(op, last_real_token_type).is_some() =>
+            {
+                // The break is made after the operator instead
+            }
+            (Some(_), _) if trailing_operator_precedence.is_some() => {
+                if let Some(precedence) = trailing_operator_precedence {
+                    self.update_operator_precedences(node, precedence, is_break);
+                }
+            }
             (_, Some(op @ (TT::Op(_) | TT::Keyword(_))))
                 if self
                     .get_next_real_token_type_from_line_index(line_index)
@@ -681,12 +746,12 @@ impl<'a> SpecificContextStack<'a> {
                         (op, token_type).get_operator_precedence().is_some()
                     }) =>
             {
-                self.update_operator_precedences(node, is_break);
+                self.update_operator_precedences(node, super::HIGHEST_PRECEDENCE, is_break);
             }
             (prev, Some(op @ (TT::Op(_) | TT::Keyword(_))))
                 if op.get_operator_precedence().is_some() && is_binary(op, prev.as_ref()) =>
             {
-                self.update_operator_precedences(node, is_break);
+                self.update_operator_precedences(node, super::HIGHEST_PRECEDENCE, is_break);
             }
             _ => {
                 self.update_last_matching_context(node, context_matches!(_), |_, data| {
@@ -1714,6 +1779,15 @@ impl TokenType {
     pub(super) fn is_comment_or_compiler_directive(self) -> bool {
         matches!(self, TT::Comment(_) | TT::CompilerDirective)
     }
+    /// Whether the token is a comment that is always followed by a line break.
+    pub(super) fn is_line_ending_comment(self) -> bool {
+        matches!(
+            self,
+            TT::Comment(
+                CommentKind::IndividualLine | CommentKind::InlineLine | CommentKind::MultilineBlock
+            )
+        )
+    }
 }
 
 struct NodeRefSet {
@@ -1,4 +1,4 @@
-use std::fmt::{Debug, Write};
+use std::fmt::{Debug, Display, Write};
 
 use itertools::Itertools;
 
@@ -18,27 +18,7 @@ impl<'a, 'b, 'c> RawDebugLine<'a, 'b, 'c> {
 }
 impl Debug for RawDebugLine<'_, '_, '_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let minmax = self.line.1.get_tokens().iter().cloned().minmax();
-        let (first, mut last) = minmax.into_option().unwrap_or((0, 0));
-
-        let mut current_parent = LineParent {
-            line_index: self.line.0,
-            global_token_index: last,
-        };
-        while let Some(children) = self.iolf.line_children.get(&current_parent) {
-            let Some(&line_index) = children.line_indices.last() else {
-                break;
-            };
-            let Some(&last_token_index) = self.iolf.lines[line_index].get_tokens().last() else {
-                break;
-            };
-            last = last_token_index;
-            current_parent = LineParent {
-                line_index,
-                global_token_index: last_token_index,
-            };
-        }
-
+        let (first, last) = self.iolf.get_line_token_span(self.line);
         let included_tokens = (first..=last).flat_map(|token_index| {
             self.iolf
                 .formatted_tokens
@@ -227,6 +207,32 @@ impl Debug for DebugFormattingNode<'_, '_> {
 }
 
 impl InternalOptimisingLineFormatter<'_, '_> {
+    /// The first and last global token indices of a line, including the tokens
+    /// of its child lines.
+    pub(super) fn get_line_token_span(&self, line: (usize, &LogicalLine)) -> (usize, usize) {
+        let minmax = line.1.get_tokens().iter().cloned().minmax();
+        let (first, mut last) = minmax.into_option().unwrap_or((0, 0));
+
+        let mut current_parent = LineParent {
+            line_index: line.0,
+            global_token_index: last,
+        };
+        while let Some(children) = self.line_children.get(&current_parent) {
+            let Some(&line_index) = children.line_indices.last() else {
+                break;
+            };
+            let Some(&last_token_index) = self.lines[line_index].get_tokens().last() else {
+                break;
+            };
+            last = last_token_index;
+            current_parent = LineParent {
+                line_index,
+                global_token_index: last_token_index,
+            };
+        }
+        (first, last)
+    }
+
     pub(super) fn solution_debugging(
         &self,
         line: (usize, &LogicalLine),
@@ -327,7 +333,11 @@ impl Debug for DebugFormattingSolution<'_, '_, '_> {
             )?;
             writeln!(f, "Updated contexts:")?;
 
-            context_stack.update_contexts(&mut recon_node, token_decision.decision.to_raw());
+            context_stack.update_contexts(
+                &mut recon_node,
+                token_decision.decision.to_raw(),
+                self.iolf.settings,
+            );
             context_stack.update_contexts_from_child_solutions(
                 &mut recon_node,
                 &token_decision.child_solutions,
@@ -371,3 +381,270 @@ impl Debug for DebugContextDataStack<'_> {
         Ok(())
     }
 }
+
+/// A single [`FormattingContext`] from the tree constructed for a
+/// [`LogicalLine`], in a form suitable for debug output.
+///
+/// Token indices are global, i.e., they index into the file's token list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextDump {
+    pub id: usize,
+    pub parent: Option<usize>,
+    pub context_type: String,
+    pub continuation_delta: u16,
+    pub starting_token: Option<usize>,
+    pub ending_token: Option<usize>,
+}
+
+/// The tree of [`FormattingContext`]s that the [`OptimisingLineFormatter`]
+/// constructs for a single [`LogicalLine`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineContextsDump {
+    pub line_index: usize,
+    pub line_type: LogicalLineType,
+    pub contexts: Vec<ContextDump>,
+}
+
+/// Constructs the [`FormattingContext`] tree for each line, as the
+/// [`OptimisingLineFormatter`] would when formatting.
+///
+/// Contexts are listed in creation order, so a context's parent always
+/// precedes it.
+pub fn dump_contexts(tokens: &[Token], lines: &[LogicalLine]) -> Vec<LineContextsDump> {
+    let token_types: Vec<TokenType> = tokens.iter().map(|t| t.get_token_type()).collect();
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            !line.get_tokens().is_empty() && !matches!(line.get_line_type(), LLT::Voided | LLT::Eof)
+        })
+        .map(|line| dump_line_contexts(line, &token_types))
+        .collect()
+}
+
+impl Display for LineContextsDump {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "line {} ({:?}):", self.line_index, self.line_type)?;
+        let mut depths = Vec::with_capacity(self.contexts.len());
+        for ctx in &self.contexts {
+            let depth = ctx.parent.map(|parent| depths[parent] + 1).unwrap_or(0);
+            depths.push(depth);
+
+            let fmt_token = |token: Option<usize>| token.map(|t| t.to_string()).unwrap_or_default();
+            writeln!(
+                f,
+                "{:indent$}{} [{}..{}] (continuation delta: {})",
+                "",
+                ctx.context_type,
+                fmt_token(ctx.starting_token),
+                fmt_token(ctx.ending_token),
+                ctx.continuation_delta,
+                indent = (depth + 1) * 2,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn dump_line_contexts(line: (usize, &LogicalLine), token_types: &[TokenType]) -> LineContextsDump {
+    let context_tree = LineFormattingContexts::new_tree();
+    // The contexts are written into `context_tree` on construction.
+    LineFormattingContexts::new(line.1, token_types, &context_tree);
+
+    let global_index = |index: u32| line.1.get_tokens().get(index as usize).copied();
+    let contexts = context_tree
+        .into_iter()
+        .map(|node| {
+            let ctx = node.get();
+            ContextDump {
+                id: node.index(),
+                parent: node.parent().map(|parent| parent.index()),
+                context_type: format!("{:?}", ctx.context_type()),
+                continuation_delta: ctx.continuation_delta(),
+                starting_token: global_index(ctx.starting_token()),
+                ending_token: ctx.ending_token().and_then(global_index),
+            }
+        })
+        .collect();
+
+    LineContextsDump {
+        line_index: line.0,
+        line_type: line.1.get_line_type(),
+        contexts,
+    }
+}
+
+/// The maximum number of runner-up solutions included in a [`LineExplanation`].
+const MAX_EXPLAINED_RUNNERS_UP: usize = 3;
+
+/// A human-readable report of how the [`OptimisingLineFormatter`] formatted
+/// the logical line spanning a line of its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineExplanation {
+    pub line_number: u32,
+    pub report: String,
+}
+
+impl Display for LineExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.report)
+    }
+}
+
+impl InternalOptimisingLineFormatter<'_, '_> {
+    /// Finds the top-level line with tokens spanning `line_number` (1-based).
+    pub(super) fn find_line_at(&self, line_number: u32) -> Option<usize> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                line.get_parent().is_none()
+                    && !line.get_tokens().is_empty()
+                    && !matches!(line.get_line_type(), LLT::Eof | LLT::Voided)
+            })
+            .find(|&line| {
+                // Tokens reordered before parsing aren't in line number order.
+                let (first, last) = self.get_line_token_span(line);
+                self.token_line_numbers[first..=last]
+                    .iter()
+                    .any(|&(start, end)| (start..=end).contains(&line_number))
+            })
+            .map(|(line_index, _)| line_index)
+    }
+
+    pub(super) fn explain_line(
+        &self,
+        line_number: u32,
+        line: (usize, &LogicalLine),
+        solution: Option<&FormattingSolution>,
+        stats: SearchStats,
+    ) -> LineExplanation {
+        let mut report = String::new();
+        // Writing to a `String` cannot fail.
+        let _ = self.write_explanation(&mut report, line_number, line, solution, &stats);
+        LineExplanation {
+            line_number,
+            report,
+        }
+    }
+
+    fn write_explanation(
+        &self,
+        f: &mut String,
+        line_number: u32,
+        line: (usize, &LogicalLine),
+        solution: Option<&FormattingSolution>,
+        stats: &SearchStats,
+    ) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Line {line_number} is part of the `{:?}` logical line {}:",
+            line.1.get_line_type(),
+            line.0
+        )?;
+        let raw_line = format!("{:?}", RawDebugLine::new(line, self));
+        writeln!(f, "{}", raw_line.trim_start_matches(['\r', '\n']))?;
+
+        writeln!(f, "\nContexts:")?;
+        for descendant in self.get_line_and_descendants(line.0) {
+            write!(f, "{}", dump_line_contexts(descendant, &self.token_types))?;
+        }
+
+        writeln!(f, "\nSearch:")?;
+        writeln!(
+            f,
+            "  {} iterations (iteration_max: {}){}",
+            stats.iteration_count,
+            self.settings.iteration_max,
+            if stats.iteration_limit_reached {
+                ", the iteration limit was reached"
+            } else {
+                ""
+            }
+        )?;
+
+        if let Some(fallback) = stats.fallback {
+            writeln!(f, "  Formatted using the `{fallback}` fallback")?;
+        }
+
+        let Some(solution) = solution else {
+            return match stats.fallback {
+                Some(_) => writeln!(f, "  The original line breaks were kept"),
+                None if line.1.get_line_type() == LLT::AsmInstruction => {
+                    writeln!(f, "  `AsmInstruction` lines are not formatted")
+                }
+                None => writeln!(f, "  No solution was found, the line was left unchanged"),
+            };
+        };
+
+        writeln!(f, "\nDecisions (token: requirement -> decision):")?;
+        self.write_decisions(f, solution, line.1, 1)?;
+
+        writeln!(f, "\nChosen solution (penalty {}):", solution.penalty)?;
+        writeln!(
+            f,
+            "{:?}",
+            DebugPrintableLine::new(solution, solution.starting_ws, line.1, self)
+        )?;
+
+        writeln!(f, "\nRunner-up candidates:")?;
+        if stats.runners_up.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for runner_up in stats.runners_up.iter().take(MAX_EXPLAINED_RUNNERS_UP) {
+            writeln!(
+                f,
+                "  penalty {} (+{}):",
+                runner_up.penalty,
+                runner_up.penalty - solution.penalty
+            )?;
+            writeln!(
+                f,
+                "{:?}",
+                DebugPrintableLine::new(runner_up, runner_up.starting_ws, line.1, self)
+            )?;
+        }
+        if let Some(omitted) = stats
+            .runners_up
+            .len()
+            .checked_sub(MAX_EXPLAINED_RUNNERS_UP)
+            .filter(|&omitted| omitted > 0)
+        {
+            writeln!(f, "  ... and {omitted} more")?;
+        }
+        Ok(())
+    }
+
+    fn write_decisions(
+        &self,
+        f: &mut String,
+        solution: &FormattingSolution,
+        line: &LogicalLine,
+        depth: usize,
+    ) -> std::fmt::Result {
+        for (&token_index, decision) in line.get_tokens().iter().zip(&solution.decisions) {
+            let content = self
+                .formatted_tokens
+                .get_token(token_index)
+                .map(|(token, _)| token.get_content())
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "{:indent$}{token_index} {content:?}: {:?}{} -> {:?}",
+                "",
+                decision.requirement,
+                if matches!(decision.requirement, DR::MustBreak | DR::MustNotBreak) {
+                    " (forced)"
+                } else {
+                    ""
+                },
+                decision.decision,
+                indent = depth * 2,
+            )?;
+            for (child_line_index, child_solution) in &decision.child_solutions {
+                self.write_decisions(f, child_solution, &self.lines[*child_line_index], depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
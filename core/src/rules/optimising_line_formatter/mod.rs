@@ -2,16 +2,17 @@
 //! (where possible) of the line length limit.
 //!
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::BinaryHeap;
 use std::collections::hash_map::Entry;
 use std::rc::Rc;
 
 use debug::DebugFormattingNode;
 use debug::RawDebugLine;
+pub use debug::{ContextDump, LineContextsDump, LineExplanation, dump_contexts};
 use fxhash::FxHashMap;
 use log::log_enabled;
-use log::{error, trace};
+use log::{debug, error, trace};
 
 use contexts::*;
 use parent_pointer_tree::{NodeRef, ParentPointerTree};
@@ -28,14 +29,125 @@ use crate::prelude::*;
 
 pub struct OptimisingLineFormatterSettings {
     pub max_line_length: u32,
+    /// The largest number of consecutive blank lines kept before a line.
+    pub max_blank_lines: u16,
     pub iteration_max: u32,
+    pub iteration_limit_fallback: IterationLimitFallback,
     pub break_before_begin: bool,
+    /// Whether the `else` of an `if` statement is placed on the same line as
+    /// the `end` before it, e.g. `end else begin`.
+    pub cuddle_else: bool,
+    /// Whether the single statement of a control flow statement without an
+    /// `else`, e.g. `if A then Exit;`, can be placed on the same line.
+    pub allow_single_line_statements: bool,
     pub format_multiline_strings: bool,
+    /// Whether each unit in a `uses`, `contains` or `requires` clause is put
+    /// on its own line, rather than wrapped at `max_line_length`.
+    pub one_import_per_line: bool,
+    /// How the parameters of a routine header are broken onto lines.
+    pub parameter_layout: ParameterLayout,
+    /// How the arguments of a routine invocation are broken onto lines.
+    pub argument_layout: ArgumentLayout,
+    pub operator_position: OperatorPosition,
+    /// Where the `:=` of an assignment is placed when the line is broken
+    /// around it. The `:=` of a `for` loop always ends the line.
+    pub assign_position: OperatorPosition,
+}
+
+impl Default for OptimisingLineFormatterSettings {
+    fn default() -> Self {
+        Self {
+            max_line_length: 120,
+            max_blank_lines: 1,
+            iteration_max: 20_000,
+            iteration_limit_fallback: IterationLimitFallback::default(),
+            break_before_begin: false,
+            cuddle_else: false,
+            allow_single_line_statements: false,
+            format_multiline_strings: true,
+            one_import_per_line: true,
+            parameter_layout: ParameterLayout::default(),
+            argument_layout: ArgumentLayout::default(),
+            operator_position: OperatorPosition::Before,
+            assign_position: OperatorPosition::After,
+        }
+    }
+}
+
+/// How the parameters of a routine header are broken onto lines.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ParameterLayout {
+    /// The parameters are wrapped at `max_line_length`, with as many on each
+    /// line as will fit.
+    BinPack,
+    /// Each parameter group is put on its own line, even if the header would
+    /// fit on one line.
+    OnePerLine,
+    /// Each parameter group is put on its own line, if the parameters don't
+    /// fit on one line.
+    #[default]
+    OnePerLineIfWrapped,
+}
+
+/// How a line is formatted when the search for its optimal formatting exceeds
+/// [`OptimisingLineFormatterSettings::iteration_max`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IterationLimitFallback {
+    /// The search is repeated, only exploring the best successors of each
+    /// decision. If this fails to find a solution, the original line breaks
+    /// are kept where possible.
+    #[default]
+    Greedy,
+    /// The original line breaks of the line and its child lines are kept, and
+    /// the continued lines are indented by a single continuation.
+    Original,
+}
+
+impl std::fmt::Display for IterationLimitFallback {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            IterationLimitFallback::Greedy => "greedy",
+            IterationLimitFallback::Original => "original",
+        })
+    }
+}
+
+/// How the arguments of a routine invocation are broken onto lines.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentLayout {
+    /// The arguments are wrapped at `max_line_length`, with as many on each
+    /// line as will fit.
+    BinPack,
+    /// The arguments are all on one line, or each on its own line, with the
+    /// closing bracket after the last argument.
+    OnePerLine,
+    /// The arguments are all on one line, or each on its own line, with the
+    /// closing bracket on its own line outside of expressions.
+    #[default]
+    OnePerLineBreakClose,
+}
+
+/// Where binary operators are placed when an expression is broken at them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorPosition {
+    /// The operator starts the continuation line.
+    #[default]
+    Before,
+    /// The operator ends the line before the continuation line.
+    After,
 }
 
 pub struct OptimisingLineFormatter {
     olf_settings: OptimisingLineFormatterSettings,
     recon_settings: ReconstructionSettings,
+    explanation_request: Option<ExplanationRequest>,
+}
+
+type ExplanationSink = Box<dyn Fn(LineExplanation) + Send + Sync>;
+
+struct ExplanationRequest {
+    line_number: u32,
+    sink: ExplanationSink,
 }
 
 /// Realistically, the [`OptimisingLineFormatter`] is a
@@ -59,6 +171,12 @@ impl LogicalLineFileFormatter for OptimisingLineFormatter {
             })
             .collect();
 
+        let token_line_numbers = Self::get_token_line_numbers(formatted_tokens);
+        let input_line_breaks = formatted_tokens
+            .tokens()
+            .map(|(_, formatting_data)| formatting_data.newlines_before > 0)
+            .collect();
+
         let mut olf = InternalOptimisingLineFormatter {
             settings: &self.olf_settings,
             recon_settings: &self.recon_settings,
@@ -67,17 +185,26 @@ impl LogicalLineFileFormatter for OptimisingLineFormatter {
             line_children: &line_children,
             token_types,
             token_lengths,
+            token_line_numbers,
+            input_line_breaks,
             child_line_cache: Default::default(),
+            greedy_search: Cell::new(false),
+            iteration_limit_reached: Cell::new(false),
+            no_new_breaks: Cell::new(false),
         };
 
+        let explained_line = self.explanation_request.as_ref().and_then(|request| {
+            olf.find_line_at(request.line_number)
+                .map(|line_index| (line_index, request.line_number))
+        });
+        let mut explanation = None;
+
         for line in input
             .iter()
             .enumerate()
             .filter(|(_, line)| line.get_parent().is_none() && line.get_line_type() != LLT::Eof)
         {
-            if let Some(solution) = olf.format_line(line) {
-                olf.reconstruct_solution(&solution, line.1);
-            }
+            olf.format_and_reconstruct_line(line, explained_line, &mut explanation);
         }
 
         /*
@@ -110,6 +237,7 @@ impl LogicalLineFileFormatter for OptimisingLineFormatter {
             not cause any multi-line strings to change in indentation.
         */
         if !self.olf_settings.format_multiline_strings {
+            self.emit_explanation(explanation);
             return;
         }
 
@@ -134,10 +262,9 @@ impl LogicalLineFileFormatter for OptimisingLineFormatter {
         lines_to_reflow.dedup_by_key(|line| line.0);
 
         for line in lines_to_reflow {
-            if let Some(solution) = olf.format_line(line) {
-                olf.reconstruct_solution(&solution, line.1);
-            }
+            olf.format_and_reconstruct_line(line, explained_line, &mut explanation);
         }
+        self.emit_explanation(explanation);
     }
 }
 impl OptimisingLineFormatter {
@@ -148,9 +275,53 @@ impl OptimisingLineFormatter {
         OptimisingLineFormatter {
             olf_settings,
             recon_settings,
+            explanation_request: None,
         }
     }
 
+    /// Requests an explanation of how the logical line spanning `line_number`
+    /// (1-based) in the input is formatted. The explanation is passed to `sink`
+    /// for each input containing such a line.
+    pub fn with_explanation(
+        mut self,
+        line_number: u32,
+        sink: impl Fn(LineExplanation) + Send + Sync + 'static,
+    ) -> Self {
+        self.explanation_request = Some(ExplanationRequest {
+            line_number,
+            sink: Box::new(sink),
+        });
+        self
+    }
+
+    fn emit_explanation(&self, explanation: Option<LineExplanation>) {
+        if let (Some(request), Some(explanation)) = (&self.explanation_request, explanation) {
+            (request.sink)(explanation);
+        }
+    }
+
+    /// Finds the first and last (1-based) line numbers of each token in the
+    /// input.
+    ///
+    /// Line numbers are derived from the whitespace, so this must be done
+    /// before any tokens are moved by this formatter. Tokens that were
+    /// reordered before parsing keep the line numbers of their positions in the
+    /// input.
+    fn get_token_line_numbers(formatted_tokens: &FormattedTokens) -> Vec<(u32, u32)> {
+        let mut line_numbers = vec![(0, 0); formatted_tokens.len()];
+        let mut current_line = 1;
+        for token_index in formatted_tokens.input_order() {
+            let Some((token, formatting_data)) = formatted_tokens.get_token(token_index) else {
+                continue;
+            };
+            current_line += formatting_data.newlines_before as u32;
+            let start_line = current_line;
+            current_line += token.get_content().matches('\n').count() as u32;
+            line_numbers[token_index] = (start_line, current_line);
+        }
+        line_numbers
+    }
+
     /// Creates a map between tokens and their child lines.
     ///
     /// A line's direct parent doesn't necessarily lead to a sequential
@@ -299,17 +470,249 @@ struct InternalOptimisingLineFormatter<'this, 'token> {
     /// has lifetime issues with recursion and looping.
     child_line_cache:
         RefCell<FxHashMap<ChildLineInitialConditions, Vec<(usize, FormattingSolution)>>>,
+    /// The first and last line number of each token in the input.
+    token_line_numbers: Vec<(u32, u32)>,
+    /// Whether each token was at the start of a line in the input.
+    input_line_breaks: Vec<bool>,
+    /// Set while searching with [`IterationLimitFallback::Greedy`], after the
+    /// full search has reached the iteration limit.
+    ///
+    /// Only the successors of the most recently explored node are kept on the
+    /// heap, so the search is not limited.
+    greedy_search: Cell<bool>,
+    /// Set when any search, including those for child lines, reaches the
+    /// iteration limit while formatting a line.
+    iteration_limit_reached: Cell<bool>,
+    /// Set while formatting a line preceded by a `pasfmt nowrap` comment, to
+    /// make `Indifferent` decisions without a line break in the input
+    /// `MustNotBreak`.
+    no_new_breaks: Cell<bool>,
 }
 
 impl<'this> InternalOptimisingLineFormatter<'this, '_> {
-    fn format_line(&self, line: (usize, &LogicalLine)) -> Option<FormattingSolution> {
+    /// Formats a top-level line, explaining the formatting if it is the line in
+    /// `explained_line`. As lines can be formatted more than once, a previous
+    /// explanation is replaced.
+    fn format_and_reconstruct_line(
+        &mut self,
+        line: (usize, &LogicalLine),
+        explained_line: Option<(usize, u32)>,
+        explanation: &mut Option<LineExplanation>,
+    ) {
+        let line_number = explained_line
+            .filter(|&(line_index, _)| line_index == line.0)
+            .map(|(_, line_number)| line_number);
+        let mut stats = line_number.map(|_| SearchStats::default());
+
+        let nowrap = is_nowrap_line(self.formatted_tokens, line.1);
+        if nowrap {
+            self.set_no_new_breaks(true);
+        }
+
+        let solution = match self.format_line(line, stats.as_mut()) {
+            Ok(solution) => solution,
+            Err(FormattingSolutionError::NoSolutionFound) => None,
+            Err(FormattingSolutionError::IterationLimitReached) => {
+                let (fallback, solution) = self.format_line_with_fallback(line);
+                if let Some(stats) = &mut stats {
+                    stats.fallback = Some(fallback);
+                }
+                solution
+            }
+        };
+        if let (Some(line_number), Some(stats)) = (line_number, stats) {
+            *explanation = Some(self.explain_line(line_number, line, solution.as_ref(), stats));
+        }
+        if let Some(solution) = solution {
+            self.reconstruct_solution(&solution, line.1);
+        }
+
+        if nowrap {
+            self.set_no_new_breaks(false);
+        }
+    }
+
+    fn set_no_new_breaks(&self, no_new_breaks: bool) {
+        self.no_new_breaks.set(no_new_breaks);
+        // Child line solutions depend on whether new line breaks are allowed.
+        self.child_line_cache.borrow_mut().clear();
+    }
+
+    /// Formats a line using the configured [`IterationLimitFallback`], reporting
+    /// a [`Diagnostic`] for the line.
+    ///
+    /// If the greedy search fails to find a solution, the original line breaks
+    /// are kept as-is.
+    fn format_line_with_fallback(
+        &mut self,
+        line: (usize, &LogicalLine),
+    ) -> (IterationLimitFallback, Option<FormattingSolution>) {
+        let solution = match self.settings.iteration_limit_fallback {
+            IterationLimitFallback::Greedy => self.format_line_with_greedy_search(line),
+            IterationLimitFallback::Original => None,
+        };
+        let (fallback, solution) = match solution {
+            Some(solution) => (IterationLimitFallback::Greedy, Some(solution)),
+            None => {
+                self.keep_original_breaks(line.0);
+                (IterationLimitFallback::Original, None)
+            }
+        };
+
+        let first_token = line.1.get_tokens().first().copied().unwrap_or_default();
+        self.formatted_tokens.add_diagnostic(Diagnostic {
+            line_number: self.token_line_numbers[first_token].0,
+            kind: DiagnosticKind::IterationLimitReached {
+                iteration_max: self.settings.iteration_max,
+                fallback,
+            },
+        });
+        (fallback, solution)
+    }
+
+    /// Formats a line using a greedy search, which is not limited by the
+    /// iteration limit.
+    fn format_line_with_greedy_search(
+        &self,
+        line: (usize, &LogicalLine),
+    ) -> Option<FormattingSolution> {
+        self.greedy_search.set(true);
+        let solution = self.format_line(line, None);
+        self.greedy_search.set(false);
+        // Child line solutions found by these searches are not necessarily optimal.
+        self.child_line_cache.borrow_mut().clear();
+        solution.ok().flatten()
+    }
+
+    /// The requirement to keep the line break (or lack thereof) before a token,
+    /// as it was in the input.
+    fn get_original_requirement(&self, line_index: u32, line: &LogicalLine) -> DR {
+        match line.get_tokens().get(line_index as usize) {
+            Some(&token_index) if token_index > 0 && self.input_line_breaks[token_index] => {
+                DR::MustBreak
+            }
+            _ => DR::MustNotBreak,
+        }
+    }
+
+    /// Keeps the existing line breaks of a line and its child lines, indenting
+    /// the continued lines by a single continuation.
+    fn keep_original_breaks(&mut self, line_index: usize) {
+        let starting_ws = LineWhitespace {
+            indentations: self.lines[line_index].get_level(),
+            continuations: 0,
+        };
+        self.keep_original_line_breaks(line_index, starting_ws, true);
+    }
+
+    fn keep_original_line_breaks(
+        &mut self,
+        line_index: usize,
+        starting_ws: LineWhitespace,
+        must_break: bool,
+    ) {
+        let line = &self.lines[line_index];
+        let continued_ws = starting_ws
+            + LineWhitespace {
+                indentations: 0,
+                continuations: 1,
+            };
+        let max_newlines = self.settings.max_blank_lines.saturating_add(1);
+        let mut token_ws = starting_ws;
+        let mut follows_child_lines = false;
+        for (index, &token_index) in line.get_tokens().iter().enumerate() {
+            if let Some(formatting_data) = self
+                .formatted_tokens
+                .get_formatting_data_mut(token_index)
+                .filter(|_| token_index > 0)
+            {
+                let is_broken = if index == 0 && must_break {
+                    formatting_data.newlines_before =
+                        formatting_data.newlines_before.clamp(1, max_newlines);
+                    true
+                } else if formatting_data.newlines_before > 0 {
+                    formatting_data.newlines_before = 1;
+                    true
+                } else {
+                    false
+                };
+                if is_broken {
+                    token_ws = if index == 0 || follows_child_lines {
+                        starting_ws
+                    } else {
+                        continued_ws
+                    };
+                    formatting_data.indentations_before = token_ws.indentations;
+                    formatting_data.continuations_before = token_ws.continuations;
+                }
+            }
+
+            let line_parent = LineParent {
+                line_index,
+                global_token_index: token_index,
+            };
+            let Some(line_children) = self.line_children.get(&line_parent) else {
+                follows_child_lines = false;
+                continue;
+            };
+            follows_child_lines = true;
+            let parent_token_type = self.get_token_type(line_children.parent_token);
+            // As with the child lines options, the bodies of control flow
+            // statements are indented from the start of the parent line.
+            let parent_ws = match parent_token_type {
+                Some(TT::Keyword(KK::Then | KK::Do | KK::Else) | TT::Op(OK::Colon)) => starting_ws,
+                _ => token_ws,
+            };
+            let first_child_token_type = line_children
+                .line_indices
+                .first()
+                .and_then(|&index| self.lines[index].get_tokens().first())
+                .and_then(|&token_index| self.get_token_type(token_index));
+            let deindent = matches!(
+                (parent_token_type, first_child_token_type),
+                (
+                    Some(TT::Keyword(KK::Then | KK::Do | KK::Else) | TT::Op(OK::Colon)),
+                    Some(TT::Keyword(KK::Begin))
+                ) | (Some(TT::Keyword(KK::Else)), Some(TT::Keyword(KK::If)))
+            );
+            for child_line_index in line_children.line_indices.clone() {
+                let child_ws = LineWhitespace {
+                    indentations: (parent_ws.indentations
+                        + self.lines[child_line_index].get_level())
+                    .saturating_sub(deindent.into()),
+                    continuations: parent_ws.continuations,
+                };
+                self.keep_original_line_breaks(child_line_index, child_ws, false);
+            }
+        }
+    }
+
+    /// Iterates a line and all the lines descending from it.
+    fn get_line_and_descendants(
+        &self,
+        line_index: usize,
+    ) -> impl Iterator<Item = (usize, &'this LogicalLine)> + use<'this> {
+        let lines = self.lines;
+        lines.iter().enumerate().filter(move |&(index, _)| {
+            std::iter::successors(Some(index), |&index| {
+                lines[index].get_parent().map(|parent| parent.line_index)
+            })
+            .any(|ancestor| ancestor == line_index)
+        })
+    }
+
+    fn format_line(
+        &self,
+        line: (usize, &LogicalLine),
+        stats: Option<&mut SearchStats>,
+    ) -> Result<Option<FormattingSolution>, FormattingSolutionError> {
         if line.1.get_line_type() == LLT::AsmInstruction {
             trace!(
                 "Skipping formatting `{:?}` line:\n{:?}",
                 line.1.get_line_type(),
                 RawDebugLine::new(line, self)
             );
-            return None;
+            return Ok(None);
         } else {
             trace!(
                 "Formatting `{:?}` line:\n{:?}",
@@ -317,6 +720,7 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
                 RawDebugLine::new(line, self)
             );
         }
+        self.iteration_limit_reached.set(false);
         let optimal_solution = self.find_optimal_solution(
             LineWhitespace {
                 indentations: line.1.get_level(),
@@ -330,23 +734,31 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
                 },
                 _ => FirstDecision::Break,
             },
+            stats,
         );
 
         optimal_solution
-            .inspect_err(|err| {
-                error!(
-                    "{} for\n{:?}",
-                    match err {
-                        FormattingSolutionError::IterationLimitReached => "Iteration limit reached",
-                        FormattingSolutionError::NoSolutionFound => "No solution found",
-                    },
-                    RawDebugLine::new(line, self),
-                )
+            .map_err(|err| match err {
+                // A child line's search reaching the limit leaves no solutions.
+                FormattingSolutionError::NoSolutionFound if self.iteration_limit_reached.get() => {
+                    FormattingSolutionError::IterationLimitReached
+                }
+                err => err,
+            })
+            .inspect_err(|err| match err {
+                FormattingSolutionError::IterationLimitReached => debug!(
+                    "Iteration limit reached for\n{:?}",
+                    RawDebugLine::new(line, self)
+                ),
+                FormattingSolutionError::NoSolutionFound => {
+                    error!("No solution found for\n{:?}", RawDebugLine::new(line, self))
+                }
             })
-            .ok()
+            .map(Some)
     }
 
     fn reconstruct_solution(&mut self, solution: &FormattingSolution, input_line: &LogicalLine) {
+        let max_newlines = self.settings.max_blank_lines.saturating_add(1);
         for (decision_index, decision) in solution.decisions.iter().enumerate() {
             let global_token_index = *input_line
                 .get_tokens()
@@ -374,7 +786,7 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
                             their statements together.
                         */
                         formatting_data.newlines_before =
-                            formatting_data.newlines_before.clamp(1, 2);
+                            formatting_data.newlines_before.clamp(1, max_newlines);
                     } else {
                         formatting_data.newlines_before = 1;
                     }
@@ -404,6 +816,7 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
         starting_ws: LineWhitespace,
         line: (usize, &LogicalLine),
         first_token_decision: FirstDecision,
+        stats: Option<&mut SearchStats>,
     ) -> Result<FormattingSolution, FormattingSolutionError> {
         let context_tree = LineFormattingContexts::new_tree();
         let formatting_contexts =
@@ -517,7 +930,12 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
         let mut node_successors = Vec::new();
 
         'node_heap: while let Some(mut node) = node_heap.pop() {
-            if iteration_count > self.settings.iteration_max {
+            if iteration_count > self.settings.iteration_max && !self.greedy_search.get() {
+                self.iteration_limit_reached.set(true);
+                if let Some(stats) = stats {
+                    stats.iteration_count = iteration_count;
+                    stats.iteration_limit_reached = true;
+                }
                 return Err(FormattingSolutionError::IterationLimitReached);
             }
             iteration_count += 1;
@@ -526,6 +944,16 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
             if node.next_line_index as usize >= line.1.get_tokens().len() {
                 let solution = node.into();
                 self.solution_debugging(line, &node_heap, iteration_count, &solution);
+                if let Some(stats) = stats {
+                    stats.iteration_count = iteration_count;
+                    stats.runners_up = node_heap
+                        .into_sorted_vec()
+                        .into_iter()
+                        .rev()
+                        .filter(|node| node.next_line_index as usize >= line.1.get_tokens().len())
+                        .map(FormattingSolution::from)
+                        .collect();
+                }
                 return Ok(solution);
             }
             if node.penalty > best_penalties[(node.next_line_index - 1) as usize] {
@@ -629,11 +1057,19 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
                         continue 'node_heap;
                     }
 
-                    let requirement = self.get_formatting_requirement(
+                    let requirement = match self.get_formatting_requirement(
                         node.next_line_index,
                         line.1,
                         &contexts.with_data(&node),
-                    );
+                    ) {
+                        DR::Indifferent if self.no_new_breaks.get() => {
+                            match self.get_original_requirement(node.next_line_index, line.1) {
+                                DR::MustNotBreak => DR::MustNotBreak,
+                                _ => DR::Indifferent,
+                            }
+                        }
+                        requirement => requirement,
+                    };
 
                     let get_solutions = |raw_decision, node, stack| {
                         self.get_potential_solution(node, line, stack, raw_decision, requirement)
@@ -733,6 +1169,9 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
                     node = node_successors.remove(0);
                     trace!("Continuing to explore single successor branch");
                 } else {
+                    if self.greedy_search.get() {
+                        node_heap.clear();
+                    }
                     node_heap.extend(node_successors.drain(..).inspect(|node| {
                         trace!(
                             "Adding successor node to heap\n{:?}",
@@ -749,6 +1188,9 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
                 }
             }
         }
+        if let Some(stats) = stats {
+            stats.iteration_count = iteration_count;
+        }
         Err(FormattingSolutionError::NoSolutionFound)
     }
 
@@ -762,7 +1204,7 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
     ) -> PotentialNodes<'this> {
         let line_index = next_node.next_line_index;
 
-        contexts.update_contexts(&mut next_node, raw_decision);
+        contexts.update_contexts(&mut next_node, raw_decision, self.settings);
         let continuation_count = contexts
             .with_data(&next_node)
             .get_continuation_count(line_index);
@@ -1010,11 +1452,22 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
                     (_, Some(TT::Keyword(KK::Begin)), _) => {
                         Potentials::One(ChildLineOption::BreakAll(parent_base_ws))
                     }
+                    (Some(false), _, 1)
+                        if self.settings.allow_single_line_statements
+                            && !must_break_first_child
+                            && line
+                                .1
+                                .get_tokens()
+                                .get(next_line_index as usize)
+                                .and_then(|&token_index| self.get_token_type(token_index))
+                                != Some(TT::Keyword(KK::Else)) =>
+                    {
+                        Potentials::Two(
+                            ChildLineOption::ContinueAll,
+                            ChildLineOption::BreakAll(parent_indented_ws),
+                        )
+                    }
                     _ => Potentials::One(ChildLineOption::BreakAll(parent_indented_ws)),
-                    /*
-                        TODO: Add setting and heuristic for unwrapping single child line
-                        e.g., `if ... then ...;`
-                    */
                 }
             }
             Some(TT::Op(OK::Colon)) => {
@@ -1117,14 +1570,24 @@ impl<'this> InternalOptimisingLineFormatter<'this, '_> {
                     .indentations
                     .saturating_sub(child_starting_ws.deindent.into());
 
-                let child_solution = self
-                    .find_optimal_solution(
-                        child_whitespace,
-                        line,
-                        get_first_token_decision(child_line_index, last_line_length),
-                    )
-                    .map(|solution| (line.0, solution))
-                    .ok()?;
+                // The cached solutions of the descendants of a `pasfmt nowrap`
+                // line are only ever found while formatting that line, so the
+                // cache does not need to be cleared.
+                let nowrap =
+                    !self.no_new_breaks.get() && is_nowrap_line(self.formatted_tokens, line.1);
+                if nowrap {
+                    self.no_new_breaks.set(true);
+                }
+                let child_solution = self.find_optimal_solution(
+                    child_whitespace,
+                    line,
+                    get_first_token_decision(child_line_index, last_line_length),
+                    None,
+                );
+                if nowrap {
+                    self.no_new_breaks.set(false);
+                }
+                let child_solution = child_solution.map(|solution| (line.0, solution)).ok()?;
                 if let Some(solution_len) = child_solution
                     .1
                     .decisions
@@ -1340,6 +1803,55 @@ impl OperatorPrecedence for (TokenType, TokenType) {
     }
 }
 
+/// Returns the precedence of the binary operator ending with the given
+/// [`TokenType`], if it is placed according to [`OperatorPosition`], by looking
+/// at its previous (real) token type.
+///
+/// Member access and the `in` of import clauses are always broken before.
+fn get_positioned_operator_precedence(
+    token_type: TokenType,
+    prev_token_type: Option<TokenType>,
+) -> Option<u8> {
+    if let Some(precedence) =
+        prev_token_type.and_then(|prev| (prev, token_type).get_operator_precedence())
+    {
+        return Some(precedence);
+    }
+    match token_type {
+        TT::Op(OK::Dot) | TT::Keyword(KK::In(InKind::Import)) => None,
+        _ if is_binary(token_type, prev_token_type.as_ref()) => {
+            token_type.get_operator_precedence()
+        }
+        _ => None,
+    }
+}
+
+/// Returns the precedence of the binary operator before a token, if the line
+/// may be broken after it with [`OperatorPosition::After`], given the types of
+/// the tokens before it in reverse order.
+///
+/// An operator after a comment which ends a line already starts a line, so it
+/// stays with the operand after it instead.
+fn get_trailing_operator_precedence(
+    prev_token_types: impl Iterator<Item = TokenType>,
+) -> Option<u8> {
+    let mut prev_token_types = prev_token_types
+        .skip_while(|token_type| token_type.is_comment_or_compiler_directive())
+        .peekable();
+    let token_type = prev_token_types.next()?;
+    let mut before_operator = prev_token_types.peek().copied();
+    let prev_real_token_type =
+        prev_token_types.find(|token_type| !token_type.is_comment_or_compiler_directive());
+    if let Some(prev) = prev_real_token_type
+        && (prev, token_type).get_operator_precedence().is_some()
+    {
+        // The operator is compound, e.g. `not in`
+        before_operator = prev_token_types.peek().copied();
+    }
+    get_positioned_operator_precedence(token_type, prev_real_token_type)
+        .filter(|_| !before_operator.is_some_and(TokenType::is_line_ending_comment))
+}
+
 /// When dealing with precedences, binary and unary operators should often be
 /// treated differently.
 ///
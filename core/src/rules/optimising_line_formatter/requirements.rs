@@ -1,10 +1,15 @@
 use super::InternalOptimisingLineFormatter;
 use super::SpecificContextDataStack;
 use super::contexts::*;
+use super::get_positioned_operator_precedence;
+use super::get_trailing_operator_precedence;
 use super::is_binary;
 use super::types::DecisionRequirement;
 use crate::lang::*;
+use crate::rules::optimising_line_formatter::ArgumentLayout;
+use crate::rules::optimising_line_formatter::OperatorPosition;
 use crate::rules::optimising_line_formatter::OperatorPrecedence;
+use crate::rules::optimising_line_formatter::ParameterLayout;
 
 use super::contexts::ContextType as CT;
 use super::types::DecisionRequirement as DR;
@@ -32,6 +37,14 @@ impl InternalOptimisingLineFormatter<'_, '_> {
         }
 
         let token_type_window = self.get_token_type_window(line_index, line);
+        let trailing_operator_precedence = match self.settings.operator_position {
+            OperatorPosition::Before => None,
+            OperatorPosition::After => get_trailing_operator_precedence(
+                (0..line_index)
+                    .rev()
+                    .flat_map(|index| self.get_token_type_for_line_index(line, index)),
+            ),
+        };
         let requirement = match token_type_window {
             (Some(TT::Op(OK::LParen)), Some(TT::Op(OK::RParen))) => contexts_data
                 .get_last_context(context_matches!(CT::Brackets(BracketKind::Round, _)))
@@ -62,11 +75,21 @@ impl InternalOptimisingLineFormatter<'_, '_> {
                     .and_then(|(_, data)| data.one_element_per_line)
                     .if_else_or_default(DR::MustBreak, DR::MustNotBreak)
             }
+            (Some(TT::Op(OK::Comma)), _)
+                if matches!(line.get_line_type(), LLT::ImportClause | LLT::ExportClause) =>
+            {
+                if !self.settings.one_import_per_line {
+                    return bin_pack_requirement(contexts_data);
+                }
+                DR::MustBreak
+            }
+            (Some(TT::Op(OK::Comma)), _)
+                if self.settings.argument_layout == ArgumentLayout::BinPack
+                    && self.is_in_argument_list(line, contexts_data) =>
+            {
+                return bin_pack_requirement(contexts_data);
+            }
             (Some(TT::Op(OK::Comma)), _) => {
-                let import_requirement =
-                    matches!(line.get_line_type(), LLT::ImportClause | LLT::ExportClause)
-                        .then_some(DR::MustBreak);
-
                 let comma_list_requirement = contexts_data
                     .get_last_context(CT::CommaList)
                     .and_then(|(_, data)| data.one_element_per_line)
@@ -79,11 +102,16 @@ impl InternalOptimisingLineFormatter<'_, '_> {
                     })
                     .if_else_map(DR::MustBreak, DR::Indifferent);
 
-                import_requirement
-                    .or(comma_list_requirement)
+                comma_list_requirement
                     .or(parens_requirement)
                     .unwrap_or_default()
             }
+            (Some(TT::Op(OK::Semicolon)), _)
+                if self.settings.parameter_layout == ParameterLayout::BinPack
+                    && is_in_parameter_list(line, contexts_data) =>
+            {
+                return bin_pack_requirement(contexts_data);
+            }
             (Some(TT::Op(OK::Semicolon)), _) => {
                 let semicolon_list_requirement = contexts_data
                     .get_last_context(CT::SemicolonList)
@@ -160,6 +188,13 @@ impl InternalOptimisingLineFormatter<'_, '_> {
             {
                 DR::MustNotBreak
             }
+            (Some(TT::Op(OK::LParen)), _)
+                if self.settings.parameter_layout == ParameterLayout::OnePerLine
+                    && is_in_parameter_list(line, contexts_data)
+                    && self.has_many_parameter_groups(line, line_index) =>
+            {
+                DR::MustBreak
+            }
             (Some(TT::Op(OK::LParen | OK::LBrack | OK::LessThan(ChK::Generic))), _) => {
                 contexts_data
                     .get_last_context(context_matches!(CT::Brackets(_, _)))
@@ -169,6 +204,12 @@ impl InternalOptimisingLineFormatter<'_, '_> {
                     .if_else_or_default(DR::MustNotBreak, DR::Indifferent)
             }
             (Some(TT::Keyword(KK::Reference)), Some(TT::Keyword(KK::To))) => DR::MustNotBreak,
+            (_, Some(TT::Op(OK::RParen)))
+                if self.settings.argument_layout == ArgumentLayout::OnePerLine
+                    && self.is_in_argument_list(line, contexts_data) =>
+            {
+                DR::MustNotBreak
+            }
             (_, Some(TT::Op(OK::RParen | OK::GreaterThan(ChK::Generic) | OK::RBrack))) => {
                 contexts_data
                     .get_last_context(context_matches!(CT::Brackets(_, _)))
@@ -180,6 +221,11 @@ impl InternalOptimisingLineFormatter<'_, '_> {
                     })
                     .if_else_or_default(DR::MustBreak, DR::MustNotBreak)
             }
+            (_, Some(TT::Op(OK::Assign))) if self.is_assign_before(line) => contexts_data
+                .get_last_context(context_matches!(CT::TypedAssignment | CT::Assignment))
+                .map(|(_, data)| data.is_broken | data.is_child_broken)
+                .if_else_or_default(DR::MustBreak, DR::Indifferent),
+            (Some(TT::Op(OK::Assign)), _) if self.is_assign_before(line) => DR::MustNotBreak,
             (_, Some(TT::Op(OK::Comma | OK::Semicolon | OK::Colon | OK::Assign))) => {
                 DR::MustNotBreak
             }
@@ -216,6 +262,12 @@ impl InternalOptimisingLineFormatter<'_, '_> {
             (Some(op1), Some(op2)) if (op1, op2).get_operator_precedence().is_some() => {
                 DR::MustNotBreak
             }
+            (prev, Some(op))
+                if self.settings.operator_position == OperatorPosition::After
+                    && get_positioned_operator_precedence(op, prev).is_some() =>
+            {
+                DR::MustNotBreak
+            }
             (prev, Some(op @ (TT::Op(_) | TT::Keyword(_))))
                 if op.get_operator_precedence().is_some() && is_binary(op, prev.as_ref()) =>
             {
@@ -226,6 +278,10 @@ impl InternalOptimisingLineFormatter<'_, '_> {
                     .and_then(|(_, data)| data.one_element_per_line)
                     .if_else_or_default(DR::MustBreak, DR::MustNotBreak)
             }
+            (Some(_), _) if trailing_operator_precedence.is_some() => trailing_operator_precedence
+                .and_then(|precedence| contexts_data.get_last_context(CT::Precedence(precedence)))
+                .and_then(|(_, data)| data.one_element_per_line)
+                .if_else_or_default(DR::MustBreak, DR::MustNotBreak),
             (
                 Some(TT::Op(OK::Equal(EqKind::Decl))),
                 Some(TT::Keyword(
@@ -281,6 +337,14 @@ impl InternalOptimisingLineFormatter<'_, '_> {
                 .get_last_context(CT::ControlFlowBegin)
                 .map(|(_, data)| data.is_child_broken)
                 .if_else_or_default(DR::MustBreak, DR::Indifferent),
+            (_, Some(TT::Keyword(KK::Else)))
+                if self.settings.cuddle_else
+                    && self.get_token_type_for_line_index(line, 0) == Some(TT::Keyword(KK::If))
+                    && self.get_prev_token_type_for_line_index(line, line_index)
+                        == Some(TT::Keyword(KK::End)) =>
+            {
+                DR::MustNotBreak
+            }
             (_, Some(TT::Keyword(KK::Else))) => contexts_data
                 .get_last_context(context_matches!(CT::IfElse))
                 .map(|(_, data)| data.is_broken | data.is_child_broken)
@@ -330,6 +394,13 @@ impl InternalOptimisingLineFormatter<'_, '_> {
         requirement.map_can_break(parents_support_break)
     }
 
+    /// Whether the line breaks around the `:=` of an assignment are made
+    /// before it.
+    fn is_assign_before(&self, line: &LogicalLine) -> bool {
+        self.settings.assign_position == OperatorPosition::Before
+            && line.get_line_type() != LLT::ForLoop
+    }
+
     pub(super) fn get_formatting_invariant(
         &self,
         line_index: u32,
@@ -382,6 +453,51 @@ impl InternalOptimisingLineFormatter<'_, '_> {
         }
     }
 
+    /// Whether the brackets opened before `line_index` contain more than one
+    /// group of semicolon separated elements.
+    fn has_many_parameter_groups(&self, line: &LogicalLine, line_index: u32) -> bool {
+        let mut depth = 0;
+        for index in line_index..line.get_tokens().len() as u32 {
+            match self.get_token_type_for_line_index(line, index) {
+                Some(TT::Op(OK::LParen | OK::LBrack)) => depth += 1,
+                Some(TT::Op(OK::RParen | OK::RBrack)) if depth == 0 => return false,
+                Some(TT::Op(OK::RParen | OK::RBrack)) => depth -= 1,
+                Some(TT::Op(OK::Semicolon)) if depth == 0 => return true,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Whether the current token is directly within the arguments of a
+    /// routine invocation, rather than in nested brackets.
+    fn is_in_argument_list(
+        &self,
+        line: &LogicalLine,
+        contexts_data: &SpecificContextDataStack,
+    ) -> bool {
+        !matches!(
+            line.get_line_type(),
+            LLT::RoutineHeader | LLT::PropertyDeclaration
+        ) && contexts_data
+            .get_last_context(context_matches!(CT::Brackets(_, _)))
+            .filter(|(ctx, _)| {
+                matches!(
+                    ctx.context_type(),
+                    CT::Brackets(
+                        BracketKind::Round,
+                        BracketStyle::BreakClose | BracketStyle::ContClose
+                    )
+                )
+            })
+            .is_some_and(|(ctx, _)| {
+                matches!(
+                    self.get_token_type_window(ctx.starting_token(), line).0,
+                    Some(TT::Identifier | TT::Op(OK::GreaterThan(ChK::Generic)))
+                )
+            })
+    }
+
     fn get_token_type_window(
         &self,
         line_index: u32,
@@ -397,6 +513,34 @@ impl InternalOptimisingLineFormatter<'_, '_> {
     }
 }
 
+/// The requirement after the separator of a list whose elements are wrapped
+/// at the line length, e.g. bin-packed arguments.
+///
+/// The elements are wrapped independently of each other, so an earlier
+/// unbroken separator doesn't prevent a later break. Within brackets, the
+/// brackets must be broken first.
+fn bin_pack_requirement(contexts_data: &SpecificContextDataStack) -> DR {
+    contexts_data
+        .get_last_context(context_matches!(CT::Brackets(_, _)))
+        .map(|(_, data)| data.is_broken)
+        .if_else_or_default(DR::Indifferent, DR::MustNotBreak)
+}
+
+/// Whether the current token is directly within the parameter list of a
+/// routine header, rather than in nested brackets.
+fn is_in_parameter_list(line: &LogicalLine, contexts_data: &SpecificContextDataStack) -> bool {
+    line.get_line_type() == LLT::RoutineHeader
+        && matches!(
+            contexts_data
+                .iter()
+                .map(|(ctx, _)| ctx.context_type())
+                .filter(|context_type| matches!(context_type, CT::Brackets(_, _)))
+                .collect::<Vec<_>>()
+                .as_slice(),
+            [CT::Brackets(BracketKind::Round, _)]
+        )
+}
+
 trait IfElse<T: Default> {
     fn if_else_or_default(self, yes: T, no: T) -> T;
     fn if_else_or(self, yes: T, no: T, el: T) -> T;
@@ -216,6 +216,20 @@ impl From<FormattingNode<'_>> for FormattingSolution {
     }
 }
 
+/// Details about the search for a line's [`FormattingSolution`], collected
+/// only when the formatting of the line is to be explained.
+#[derive(Debug, Default)]
+pub(super) struct SearchStats {
+    pub(super) iteration_count: u32,
+    pub(super) iteration_limit_reached: bool,
+    /// The fallback used to format the line, if the iteration limit was
+    /// reached.
+    pub(super) fallback: Option<super::IterationLimitFallback>,
+    /// Complete solutions that were still on the heap when the chosen solution
+    /// was found, in order of increasing penalty.
+    pub(super) runners_up: Vec<FormattingSolution>,
+}
+
 /// In a [`FormattingSolution`], there is a [`TokenDecision`] for each token in
 /// the line.
 #[derive(Debug, Clone, PartialEq, Eq)]
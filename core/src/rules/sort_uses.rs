@@ -0,0 +1,370 @@
+use crate::lang::OperatorKind as OK;
+use crate::lang::RawTokenType as RTT;
+use crate::prelude::*;
+use crate::rules::formatting_toggle::{is_toggle_comment, is_toggled_off};
+
+/// Sorts the units in `uses` clauses, case-insensitively, grouped by their
+/// namespace.
+///
+/// Comments on their own lines stay with the unit after them, and comments
+/// after a unit on the same line stay with that unit. Clauses containing
+/// compiler directives, e.g. `{$IFDEF}`, are left as they are.
+///
+/// The clauses are sorted before parsing, so the logical lines are the same
+/// as if the units were written in sorted order. The moved tokens are reported
+/// through [`RawTokenConsolidator::consolidate_with_origins`], so that cursors
+/// and line numbers still refer to the input.
+pub struct SortUses {
+    /// The namespace prefixes of the groups, in order. Units are put in the
+    /// group with the longest matching prefix. Units without a matching prefix
+    /// are put in the `*` group, or after all the groups if there isn't one.
+    pub group_order: Vec<String>,
+}
+
+impl Default for SortUses {
+    fn default() -> Self {
+        SortUses {
+            group_order: ["System", "Winapi", "Vcl", "FMX", "Data", "*"]
+                .map(String::from)
+                .to_vec(),
+        }
+    }
+}
+
+/// A unit in a `uses` clause, as ranges of the clause's tokens.
+struct UsedUnit {
+    /// The comments on the lines before the unit.
+    leading: std::ops::Range<usize>,
+    /// The unit name, and any `in` path.
+    body: std::ops::Range<usize>,
+    /// The comments after the unit's separator.
+    trailing: std::ops::Range<usize>,
+    name: String,
+}
+
+fn is_comment(token: &RawToken) -> bool {
+    matches!(token.get_token_type(), RTT::Comment(_))
+}
+
+fn is_own_line_comment(token: &RawToken) -> bool {
+    is_comment(token) && token.get_leading_whitespace().contains('\n')
+}
+
+/// Splits a clause, from its first unit up to and including the `;`, into
+/// its units. Returns `None` if the clause can't be sorted.
+fn parse_units(clause: &[RawToken]) -> Option<Vec<UsedUnit>> {
+    let mut units = vec![];
+    let mut index = 0;
+    loop {
+        let leading_start = index;
+        while clause.get(index).is_some_and(is_comment) {
+            index += 1;
+        }
+        let body_start = index;
+        let mut name = String::new();
+        let mut in_path = false;
+        let separator = loop {
+            let token = clause.get(index)?;
+            match token.get_token_type() {
+                RTT::Op(separator @ (OK::Comma | OK::Semicolon)) => break separator,
+                RTT::Keyword(KeywordKind::In(_)) => in_path = true,
+                RTT::Comment(_) => {}
+                RTT::CompilerDirective | RTT::ConditionalDirective(_) | RTT::Eof => return None,
+                _ if !in_path => name.push_str(token.get_content()),
+                _ => {}
+            }
+            index += 1;
+        };
+        if name.is_empty() {
+            return None;
+        }
+        let body = body_start..index;
+
+        index += 1;
+        let trailing_start = index;
+        while clause
+            .get(index)
+            .is_some_and(|token| is_comment(token) && !is_own_line_comment(token))
+        {
+            index += 1;
+        }
+        units.push(UsedUnit {
+            leading: leading_start..body_start,
+            body,
+            trailing: trailing_start..index,
+            name: name.to_lowercase(),
+        });
+
+        if separator == OK::Semicolon {
+            return (index == clause.len()).then_some(units);
+        }
+    }
+}
+
+impl SortUses {
+    fn group(&self, name: &str) -> usize {
+        let mut group = self
+            .group_order
+            .iter()
+            .position(|prefix| prefix == "*")
+            .unwrap_or(self.group_order.len());
+        let mut group_len = 0;
+        for (index, prefix) in self.group_order.iter().enumerate() {
+            let prefix = prefix.to_lowercase();
+            let matches = name == prefix
+                || name
+                    .strip_prefix(&prefix)
+                    .is_some_and(|rest| rest.starts_with('.'));
+            if matches && prefix.len() > group_len {
+                group = index;
+                group_len = prefix.len();
+            }
+        }
+        group
+    }
+
+    /// The new order of the clause's tokens, if it needs to change.
+    fn sorted_order(&self, clause: &[RawToken]) -> Option<Vec<usize>> {
+        let units = parse_units(clause)?;
+        let mut sorted: Vec<&UsedUnit> = units.iter().collect();
+        sorted.sort_by_cached_key(|unit| (self.group(&unit.name), unit.name.clone()));
+        if sorted
+            .iter()
+            .zip(&units)
+            .all(|(sorted, unit)| std::ptr::eq(*sorted, unit))
+        {
+            return None;
+        }
+
+        // The separators stay in place, so the last unit is followed by the `;`.
+        let separators = units.iter().map(|unit| unit.body.end);
+        let mut order = vec![];
+        for (unit, separator) in sorted.into_iter().zip(separators) {
+            order.extend(unit.leading.clone());
+            order.extend(unit.body.clone());
+            order.push(separator);
+            order.extend(unit.trailing.clone());
+        }
+        Some(order)
+    }
+}
+
+/// Moves the items so that the item at `order[i]` is at index `i`.
+///
+/// Each cycle of the permutation is followed once, swapping its items into
+/// place.
+fn reorder<T>(items: &mut [T], order: &[usize]) {
+    let mut done = vec![false; order.len()];
+    for start in 0..order.len() {
+        let mut index = start;
+        while !done[index] {
+            done[index] = true;
+            let next = order[index];
+            if next == start {
+                break;
+            }
+            items.swap(index, next);
+            index = next;
+        }
+    }
+}
+
+impl SortUses {
+    /// Sorts the units of the clause after the `uses` at `uses_index`.
+    fn sort_clause(&self, tokens: &mut [RawToken], origins: &mut [usize], uses_index: usize) {
+        let Some(semicolon) = (uses_index + 1..tokens.len())
+            .find(|&index| tokens[index].get_token_type() == RTT::Op(OK::Semicolon))
+        else {
+            return;
+        };
+        // Comments after the `;` on the same line stay with the last unit.
+        let last = (semicolon + 1..tokens.len())
+            .take_while(|&index| is_comment(&tokens[index]) && !is_own_line_comment(&tokens[index]))
+            .last()
+            .unwrap_or(semicolon);
+        if is_toggled_off(tokens, uses_index)
+            || tokens[uses_index..=last].iter().any(is_toggle_comment)
+        {
+            return;
+        }
+
+        // Comments after `uses` on the same line stay where they are, and
+        // comments on the lines after it are part of the first unit.
+        let Some(start) = (uses_index + 1..=last)
+            .find(|&index| !is_comment(&tokens[index]) || is_own_line_comment(&tokens[index]))
+        else {
+            return;
+        };
+        if let Some(order) = self.sorted_order(&tokens[start..=last]) {
+            reorder(&mut tokens[start..=last], &order);
+            reorder(&mut origins[start..=last], &order);
+        }
+    }
+}
+
+impl RawTokenConsolidator for SortUses {
+    fn consolidate(&self, tokens: &mut [RawToken]) {
+        let mut origins: Vec<usize> = (0..tokens.len()).collect();
+        self.consolidate_with_origins(tokens, &mut origins);
+    }
+
+    fn consolidate_with_origins(&self, tokens: &mut [RawToken], origins: &mut [usize]) {
+        for uses_index in 0..tokens.len() {
+            if tokens[uses_index].get_token_type() == RTT::Keyword(KeywordKind::Uses) {
+                self.sort_clause(tokens, origins, uses_index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    fn formatter_with(sort_uses: SortUses) -> Formatter {
+        let recon = default_test_reconstruction_settings();
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .raw_token_consolidator(sort_uses)
+            .parser(DelphiLogicalLineParser {})
+            .token_consolidator(DistinguishGenericTypeParamsConsolidator {})
+            .lines_consolidator(ConditionalDirectiveConsolidator {})
+            .token_ignorer(FormattingToggler {})
+            .file_formatter(TokenSpacing {})
+            .file_formatter(OptimisingLineFormatter::new(
+                OptimisingLineFormatterSettings {
+                    max_line_length: 40,
+                    ..Default::default()
+                },
+                recon.clone(),
+            ))
+            .reconstructor(DelphiLogicalLinesReconstructor::new(recon))
+            .build()
+    }
+
+    fn formatter() -> Formatter {
+        formatter_with(SortUses::default())
+    }
+
+    formatter_test_group!(
+        default_order,
+        alphabetical = {
+            "uses c, A, b;\n",
+            "uses\n  A,\n  b,\n  c;\n",
+        },
+        grouped_by_namespace = {
+            "uses MyUnit, Data.DB, Vcl.Forms, System.Classes, System.SysUtils, Winapi.Windows;\n",
+            indoc! {"
+                uses
+                  System.Classes,
+                  System.SysUtils,
+                  Winapi.Windows,
+                  Vcl.Forms,
+                  Data.DB,
+                  MyUnit;
+            "},
+        },
+        namespace_is_whole_word = {
+            "uses SystemUtils, System;\n",
+            "uses\n  System,\n  SystemUtils;\n",
+        },
+        in_paths = {
+            "program P; uses B in 'B.pas', A in 'A.pas';\n",
+            "program P;\nuses\n  A in 'A.pas',\n  B in 'B.pas';\n",
+        },
+        comments_stay_with_units = {
+            indoc! {"
+                uses
+                  // the b unit
+                  B, // b
+                  A, // a
+                  C { c };
+            "},
+            indoc! {"
+                uses
+                  A, // a
+                  // the b unit
+                  B, // b
+                  C { c };
+            "},
+        },
+        trailing_comment_on_last_unit = {
+            "uses\n  B; // b\n",
+            "uses\n  B; // b\n",
+        },
+        trailing_comment_moves_from_last_unit = {
+            "uses\n  B,\n  A; // a\n",
+            "uses\n  A, // a\n  B;\n",
+        },
+        comment_sorted_to_front = {
+            "uses\n  C,\n  // note\n  A,\n  B;\n",
+            "uses\n  // note\n  A,\n  B,\n  C;\n",
+        },
+        comment_sorted_from_front = {
+            "uses\n  // note\n  C,\n  // mid\n  A;\n",
+            "uses\n  // mid\n  A,\n  // note\n  C;\n",
+        },
+        comment_after_uses_stays = {
+            "uses // units\n  B,\n  A;\n",
+            "uses // units\n  A,\n  B;\n",
+        },
+        conditional_directives_prevent_sorting = {
+            "uses\n  B,\n  {$IFDEF X}\n  A,\n  {$ENDIF}\n  C;\n",
+            "uses\n  B,\n{$IFDEF X}\n  A,\n{$ENDIF}\n  C;\n",
+        },
+        ignored_clauses_are_not_sorted = {
+            "// pasfmt off\nuses B, A;\n// pasfmt on\n",
+            "// pasfmt off\nuses B, A;\n// pasfmt on\n",
+        },
+        skipped_clauses_are_not_sorted = {
+            "// pasfmt skip\nuses B, A;\n",
+            "// pasfmt skip\nuses B, A;\n",
+        },
+    );
+
+    #[test]
+    fn reorder_follows_cycles() {
+        let mut items = ['a', 'b', 'c', 'd', 'e', 'f'];
+        reorder(&mut items, &[2, 0, 1, 4, 3, 5]);
+        assert_eq!(items, ['c', 'a', 'b', 'e', 'd', 'f']);
+    }
+
+    #[yare::parameterized(
+        comment_sorted_to_front = { "uses\n  C,\n  // note\n  A,\n  B;\n" },
+        comment_sorted_from_front = { "uses\n  // note\n  C,\n  // mid\n  A;\n" },
+        trailing_comment_moves_from_last_unit = { "uses\n  B,\n  A; // a\n" },
+    )]
+    fn sorting_is_idempotent(input: &str) {
+        let formatter = formatter();
+        let formatted_once = formatter.format(input, FileOptions::new());
+        let formatted_twice = formatter.format(&formatted_once, FileOptions::new());
+        pretty_assertions::assert_eq!(formatted_once, formatted_twice);
+    }
+
+    mod custom_order {
+        use super::*;
+
+        fn formatter() -> Formatter {
+            formatter_with(SortUses {
+                group_order: ["*", "Vcl", "System", "System.Generics"]
+                    .map(String::from)
+                    .to_vec(),
+            })
+        }
+
+        formatter_test_group!(
+            tests,
+            custom_order = {
+                "uses System.Generics.Collections, System.Classes, Vcl.Forms, MyUnit;\n",
+                indoc! {"
+                    uses
+                      MyUnit,
+                      Vcl.Forms,
+                      System.Classes,
+                      System.Generics.Collections;
+                "},
+            },
+        );
+    }
+}
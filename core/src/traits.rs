@@ -7,6 +7,15 @@ pub trait Lexer {
 
 pub trait RawTokenConsolidator {
     fn consolidate(&self, tokens: &mut [RawToken]);
+
+    /// Consolidates the tokens, moving the entries of `origins` along with
+    /// any tokens that are reordered.
+    ///
+    /// `origins[i]` is the index of the token at `i` in the lexed input.
+    fn consolidate_with_origins(&self, tokens: &mut [RawToken], origins: &mut [usize]) {
+        let _ = origins;
+        self.consolidate(tokens);
+    }
 }
 pub trait TokenConsolidator {
     fn consolidate(&self, tokens: &mut [Token]);
@@ -38,6 +47,11 @@ pub trait LogicalLineFileFormatter {
 pub trait CursorTracker {
     fn relocate_cursors(&mut self, formatted_tokens: &FormattedTokens);
     fn notify_token_deleted(&mut self, deleted_token: usize);
+    /// Called when the tokens have been reordered, where `origins[i]` is the
+    /// previous index of the token now at `i`.
+    fn notify_tokens_moved(&mut self, origins: &[usize]) {
+        let _ = origins;
+    }
 }
 
 pub trait LogicalLinesReconstructor {
@@ -0,0 +1,100 @@
+use std::cmp::{Ordering, Reverse};
+
+use pasfmt_orchestrator::predule::SourceSample;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Whitespace conventions counted across the sampled source files.
+#[derive(Default)]
+pub(crate) struct SourceStyle {
+    crlf_lines: usize,
+    lf_lines: usize,
+    tab_indented_lines: usize,
+    space_indented_lines: usize,
+    /// The number of times each indentation step (in spaces) was seen between
+    /// consecutive space-indented lines, indexed by the step.
+    indent_steps: [usize; 9],
+    non_ascii_utf8_files: usize,
+    non_utf8_files: usize,
+}
+
+impl SourceStyle {
+    pub(crate) fn from_samples(samples: &[SourceSample]) -> Self {
+        let mut style = Self::default();
+        for sample in samples {
+            style.add_sample(&sample.contents);
+        }
+        style
+    }
+
+    fn add_sample(&mut self, contents: &[u8]) {
+        let contents = contents.strip_prefix(UTF8_BOM).unwrap_or(contents);
+        match std::str::from_utf8(contents) {
+            Ok(text) if !text.is_ascii() => self.non_ascii_utf8_files += 1,
+            Ok(_) => {}
+            Err(_) => self.non_utf8_files += 1,
+        }
+
+        let mut previous_spaces = 0;
+        for line in contents.split_inclusive(|&b| b == b'\n') {
+            if line.ends_with(b"\r\n") {
+                self.crlf_lines += 1;
+            } else if line.ends_with(b"\n") {
+                self.lf_lines += 1;
+            }
+
+            let content = line.trim_ascii_start();
+            if content.is_empty() {
+                continue;
+            }
+            let indentation = &line[..line.len() - content.len()];
+            if indentation.starts_with(b"\t") {
+                self.tab_indented_lines += 1;
+            } else if !indentation.is_empty() && indentation.iter().all(|&b| b == b' ') {
+                self.space_indented_lines += 1;
+                if let Some(step) = self
+                    .indent_steps
+                    .get_mut(indentation.len().saturating_sub(previous_spaces))
+                {
+                    *step += 1;
+                }
+            }
+            previous_spaces = match indentation.iter().all(|&b| b == b' ') {
+                true => indentation.len(),
+                false => 0,
+            };
+        }
+    }
+
+    pub(crate) fn use_tabs(&self) -> Option<bool> {
+        match self.tab_indented_lines + self.space_indented_lines {
+            0 => None,
+            _ => Some(self.tab_indented_lines > self.space_indented_lines),
+        }
+    }
+
+    /// The most common non-zero indentation step between space-indented lines.
+    pub(crate) fn tab_width(&self) -> Option<u8> {
+        (1..self.indent_steps.len())
+            .filter(|&step| self.indent_steps[step] > 0)
+            .max_by_key(|&step| (self.indent_steps[step], Reverse(step)))
+            .map(|step| step as u8)
+    }
+
+    pub(crate) fn line_ending(&self) -> Option<&'static str> {
+        match self.crlf_lines.cmp(&self.lf_lines) {
+            Ordering::Greater => Some("crlf"),
+            Ordering::Less => Some("lf"),
+            Ordering::Equal => None,
+        }
+    }
+
+    /// UTF-8 is only inferred when there is evidence for it. Files with an
+    /// unknown 8-bit encoding are best left to the native encoding.
+    pub(crate) fn encoding(&self) -> Option<&'static str> {
+        match (self.non_ascii_utf8_files, self.non_utf8_files) {
+            (1.., 0) => Some("utf-8"),
+            _ => None,
+        }
+    }
+}
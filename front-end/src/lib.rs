@@ -1,9 +1,19 @@
 #![deny(clippy::enum_glob_use)]
 
+mod infer;
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::Context;
+
 use encoding_rs::Encoding;
 use pasfmt_core::prelude::*;
 use pasfmt_orchestrator::predule::*;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+
+use crate::infer::SourceStyle;
 
 #[cfg(windows)]
 fn get_windows_default_encoding() -> &'static Encoding {
@@ -31,18 +41,41 @@ fn get_windows_default_encoding() -> &'static Encoding {
     *WINDOWS_DEFAULT_ENCODING
 }
 
-#[cfg_attr(feature = "__demo", derive(serde::Serialize))]
-#[derive(Deserialize, Debug, Clone, Copy, Default)]
-#[serde(rename_all = "lowercase")]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase", try_from = "String")]
 enum LineEnding {
-    #[serde(alias = "CRLF")]
     Crlf,
-    #[serde(alias = "LF")]
     Lf,
     #[default]
     Native,
 }
 
+impl LineEnding {
+    const VALUES: &[&str] = &["lf", "crlf", "native"];
+    /// Other accepted spellings, which are the uppercase of a value.
+    const ALIASES: &[&str] = &["LF", "CRLF"];
+}
+
+impl TryFrom<String> for LineEnding {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let value = match Self::ALIASES.contains(&value.as_str()) {
+            true => value.to_lowercase(),
+            false => value,
+        };
+        match value.as_str() {
+            "lf" => Ok(LineEnding::Lf),
+            "crlf" => Ok(LineEnding::Crlf),
+            "native" => Ok(LineEnding::Native),
+            _ => Err(format!(
+                "unknown line ending `{value}`, expected one of `{}`",
+                Self::VALUES.join("`, `")
+            )),
+        }
+    }
+}
+
 impl From<LineEnding> for pasfmt_core::lang::LineEnding {
     fn from(value: LineEnding) -> Self {
         use pasfmt_core::lang::LineEnding as CoreLineEnding;
@@ -81,8 +114,7 @@ impl From<InternalEncoding> for &'static Encoding {
     }
 }
 
-#[cfg(feature = "__demo")]
-impl serde::Serialize for InternalEncoding {
+impl Serialize for InternalEncoding {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -128,24 +160,214 @@ impl<'de> Deserialize<'de> for InternalEncoding {
     }
 }
 
-#[cfg_attr(feature = "__demo", derive(serde::Serialize))]
-#[derive(Debug, Clone, Copy, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
 #[serde(rename_all = "lowercase")]
 enum BeginStyle {
     #[default]
     Auto,
     #[allow(non_camel_case_types)]
     Always_Wrap,
+    #[allow(non_camel_case_types)]
+    Cuddle_Else,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum UsesLayout {
+    Wrap,
+    #[default]
+    #[allow(non_camel_case_types)]
+    One_Per_Line,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+#[allow(non_camel_case_types)]
+enum ParameterLayout {
+    Bin_Pack,
+    One_Per_Line,
+    #[default]
+    One_Per_Line_If_Wrapped,
+}
+
+impl From<ParameterLayout> for pasfmt_core::prelude::ParameterLayout {
+    fn from(value: ParameterLayout) -> Self {
+        use pasfmt_core::prelude::ParameterLayout as CoreLayout;
+
+        match value {
+            ParameterLayout::Bin_Pack => CoreLayout::BinPack,
+            ParameterLayout::One_Per_Line => CoreLayout::OnePerLine,
+            ParameterLayout::One_Per_Line_If_Wrapped => CoreLayout::OnePerLineIfWrapped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+#[allow(non_camel_case_types)]
+enum ArgumentLayout {
+    Bin_Pack,
+    One_Per_Line,
+    #[default]
+    One_Per_Line_Break_Close,
+}
+
+impl From<ArgumentLayout> for pasfmt_core::prelude::ArgumentLayout {
+    fn from(value: ArgumentLayout) -> Self {
+        use pasfmt_core::prelude::ArgumentLayout as CoreLayout;
+
+        match value {
+            ArgumentLayout::Bin_Pack => CoreLayout::BinPack,
+            ArgumentLayout::One_Per_Line => CoreLayout::OnePerLine,
+            ArgumentLayout::One_Per_Line_Break_Close => CoreLayout::OnePerLineBreakClose,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum OperatorPosition {
+    #[default]
+    Auto,
+    Before,
+    After,
+}
+
+impl OperatorPosition {
+    /// The core positions of binary operators and of `:=`, respectively.
+    fn positions(
+        self,
+    ) -> (
+        pasfmt_core::prelude::OperatorPosition,
+        pasfmt_core::prelude::OperatorPosition,
+    ) {
+        use pasfmt_core::prelude::OperatorPosition as CorePosition;
+
+        match self {
+            OperatorPosition::Auto => (CorePosition::Before, CorePosition::After),
+            OperatorPosition::Before => (CorePosition::Before, CorePosition::Before),
+            OperatorPosition::After => (CorePosition::After, CorePosition::After),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum CaseStyle {
+    #[default]
+    Lower,
+    Upper,
+    Pascal,
+    Preserve,
+}
+
+impl From<CaseStyle> for pasfmt_core::prelude::CaseStyle {
+    fn from(value: CaseStyle) -> Self {
+        use pasfmt_core::prelude::CaseStyle as CoreCaseStyle;
+
+        match value {
+            CaseStyle::Lower => CoreCaseStyle::Lower,
+            CaseStyle::Upper => CoreCaseStyle::Upper,
+            CaseStyle::Pascal => CoreCaseStyle::Pascal,
+            CaseStyle::Preserve => CoreCaseStyle::Preserve,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum DirectiveCaseStyle {
+    Lower,
+    Upper,
+    Preserve,
+}
+
+impl From<DirectiveCaseStyle> for pasfmt_core::prelude::CaseStyle {
+    fn from(value: DirectiveCaseStyle) -> Self {
+        use pasfmt_core::prelude::CaseStyle as CoreCaseStyle;
+
+        match value {
+            DirectiveCaseStyle::Lower => CoreCaseStyle::Lower,
+            DirectiveCaseStyle::Upper => CoreCaseStyle::Upper,
+            DirectiveCaseStyle::Preserve => CoreCaseStyle::Preserve,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+enum IterationLimitFallback {
+    #[default]
+    Greedy,
+    Original,
+}
+
+impl From<IterationLimitFallback> for pasfmt_core::prelude::IterationLimitFallback {
+    fn from(value: IterationLimitFallback) -> Self {
+        use pasfmt_core::prelude::IterationLimitFallback as CoreFallback;
+
+        match value {
+            IterationLimitFallback::Greedy => CoreFallback::Greedy,
+            IterationLimitFallback::Original => CoreFallback::Original,
+        }
+    }
+}
+
+/// The formatting rules which can be individually disabled, in the `[rules]`
+/// table.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(deny_unknown_fields)]
+#[serde(default)]
+struct Rules {
+    keyword_case: bool,
+    identifier_case: bool,
+    sort_uses: bool,
+    comment_contents: bool,
+    directive_case: bool,
+    eof_newline: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            keyword_case: true,
+            identifier_case: false,
+            sort_uses: false,
+            comment_contents: true,
+            directive_case: true,
+            eof_newline: true,
+        }
+    }
 }
 
-#[cfg_attr(feature = "__demo", derive(serde::Serialize))]
-#[derive(Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 #[serde(default)]
 pub struct FormattingConfig {
     wrap_column: u32,
     begin_style: BeginStyle,
+    uses_layout: UsesLayout,
+    parameter_layout: ParameterLayout,
+    argument_layout: ArgumentLayout,
+    operator_position: OperatorPosition,
+    allow_single_line_statements: bool,
     format_multiline_strings: bool,
+    iteration_max: u32,
+    iteration_limit_fallback: IterationLimitFallback,
+    keyword_case: CaseStyle,
+    impure_keyword_case: CaseStyle,
+    directive_case: DirectiveCaseStyle,
+    directive_argument_case: DirectiveCaseStyle,
+    #[serde(deserialize_with = "string_or_list")]
+    casing_dictionary: Vec<String>,
+    align_declarations: bool,
+    align_trailing_comments: bool,
+    max_blank_lines: u16,
+    blank_lines_between_routines: u16,
+    blank_lines_before_sections: u16,
+    trim_blank_lines_in_blocks: bool,
+    #[serde(deserialize_with = "string_or_list")]
+    uses_group_order: Vec<String>,
 
     encoding: InternalEncoding,
 
@@ -153,13 +375,74 @@ pub struct FormattingConfig {
     tab_width: u8,
     continuation_indents: u8,
     line_ending: LineEnding,
+
+    #[serde(deserialize_with = "empty_table_as_default")]
+    rules: Rules,
+}
+
+fn string_or_list<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrList {
+        String(String),
+        List(Vec<String>),
+    }
+
+    Ok(match Option::<StringOrList>::deserialize(deserializer)? {
+        None => vec![],
+        Some(StringOrList::String(s)) if s.is_empty() => vec![],
+        Some(StringOrList::String(s)) => vec![s],
+        Some(StringOrList::List(list)) => list,
+    })
+}
+
+/// The configuration loader reads an empty table (e.g. a `[rules]` header
+/// with all of its options commented out) as a unit value.
+fn empty_table_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
 }
 
 impl FormattingConfig {
+    const BUILTIN_CASING_DICTIONARY: &str = "builtin";
+
     #[cfg(feature = "__demo")]
     pub fn max_line_length(&self) -> u32 {
         self.wrap_column
     }
+
+    /// Makes the relative paths in the configuration relative to `base_dir`.
+    fn resolve_paths(&mut self, base_dir: &Path) {
+        for path in &mut self.casing_dictionary {
+            if path != Self::BUILTIN_CASING_DICTIONARY {
+                *path = base_dir.join(&*path).to_string_lossy().into_owned();
+            }
+        }
+    }
+
+    fn casing_dictionary(&self) -> anyhow::Result<Option<CasingDictionary>> {
+        if self.casing_dictionary.is_empty() {
+            return Ok(None);
+        }
+
+        let mut dictionary = CasingDictionary::new();
+        for path in &self.casing_dictionary {
+            if path == Self::BUILTIN_CASING_DICTIONARY {
+                dictionary.add_words(CasingDictionary::BUILTIN_WORD_LIST);
+            } else {
+                let word_list = std::fs::read_to_string(path)
+                    .with_context(|| format!("failed to read casing dictionary '{path}'"))?;
+                dictionary.add_words(&word_list);
+            }
+        }
+        Ok(Some(dictionary))
+    }
 }
 
 impl Default for FormattingConfig {
@@ -172,7 +455,27 @@ impl Default for FormattingConfig {
             encoding: InternalEncoding::default(),
             wrap_column: 120,
             begin_style: BeginStyle::default(),
+            uses_layout: UsesLayout::default(),
+            parameter_layout: ParameterLayout::default(),
+            argument_layout: ArgumentLayout::default(),
+            operator_position: OperatorPosition::default(),
+            allow_single_line_statements: false,
             format_multiline_strings: true,
+            iteration_max: 20_000,
+            iteration_limit_fallback: IterationLimitFallback::default(),
+            keyword_case: CaseStyle::default(),
+            impure_keyword_case: CaseStyle::default(),
+            directive_case: DirectiveCaseStyle::Upper,
+            directive_argument_case: DirectiveCaseStyle::Preserve,
+            casing_dictionary: vec![],
+            align_declarations: false,
+            align_trailing_comments: false,
+            max_blank_lines: 1,
+            blank_lines_between_routines: 0,
+            blank_lines_before_sections: 0,
+            trim_blank_lines_in_blocks: false,
+            uses_group_order: SortUses::default().group_order,
+            rules: Rules::default(),
         }
     }
 }
@@ -202,11 +505,58 @@ impl From<&FormattingConfig> for ReconstructionSettings {
 
 impl From<&FormattingConfig> for OptimisingLineFormatterSettings {
     fn from(value: &FormattingConfig) -> Self {
+        let (operator_position, assign_position) = value.operator_position.positions();
         Self {
             max_line_length: value.wrap_column,
-            iteration_max: 20_000,
+            max_blank_lines: value.max_blank_lines,
+            iteration_max: value.iteration_max,
+            iteration_limit_fallback: value.iteration_limit_fallback.into(),
             break_before_begin: matches!(value.begin_style, BeginStyle::Always_Wrap),
+            cuddle_else: matches!(value.begin_style, BeginStyle::Cuddle_Else),
+            allow_single_line_statements: value.allow_single_line_statements,
             format_multiline_strings: value.format_multiline_strings,
+            one_import_per_line: matches!(value.uses_layout, UsesLayout::One_Per_Line),
+            parameter_layout: value.parameter_layout.into(),
+            argument_layout: value.argument_layout.into(),
+            operator_position,
+            assign_position,
+        }
+    }
+}
+
+impl From<&FormattingConfig> for KeywordCase {
+    fn from(value: &FormattingConfig) -> Self {
+        Self {
+            reserved_words: value.keyword_case.into(),
+            impure_keywords: value.impure_keyword_case.into(),
+        }
+    }
+}
+
+impl From<&FormattingConfig> for SortUses {
+    fn from(value: &FormattingConfig) -> Self {
+        Self {
+            group_order: value.uses_group_order.clone(),
+        }
+    }
+}
+
+impl From<&FormattingConfig> for BlankLines {
+    fn from(value: &FormattingConfig) -> Self {
+        Self {
+            max_blank_lines: value.max_blank_lines,
+            between_routines: value.blank_lines_between_routines,
+            before_sections: value.blank_lines_before_sections,
+            trim_blocks: value.trim_blank_lines_in_blocks,
+        }
+    }
+}
+
+impl From<&FormattingConfig> for DirectiveCase {
+    fn from(value: &FormattingConfig) -> Self {
+        Self {
+            names: value.directive_case.into(),
+            arguments: value.directive_argument_case.into(),
         }
     }
 }
@@ -218,7 +568,7 @@ impl Configuration for FormattingConfig {
             ConfigItem {
                 name: "wrap_column",
                 description: "Target line length before wrapping",
-                hint: "<unsigned integer>",
+                kind: ConfigKind::unsigned(u32::MAX),
                 default: defaults.wrap_column.to_string(),
             },
             ConfigItem {
@@ -226,11 +576,74 @@ impl Configuration for FormattingConfig {
                 description: "\
 Places the `begin` after control flow statements (e.g. `if`).
 If \"always_wrap\", the `begin` will always be placed on the next line
-at the same indentation as the statement it is within.\
+at the same indentation as the statement it is within.
+If \"cuddle_else\", the `begin` is placed as with \"auto\", and the `else` of an
+`if` statement is placed on the same line as the `end` before it, e.g.
+`end else begin`.\
                     ",
-                hint: "[ auto | always_wrap ]",
+                kind: ConfigKind::OneOf(&["auto", "always_wrap", "cuddle_else"]),
                 default: format!("{:?}", defaults.begin_style).to_lowercase(),
             },
+            ConfigItem {
+                name: "uses_layout",
+                description: "\
+The layout of the units in `uses`, `contains` and `requires` clauses.
+If \"one_per_line\", each unit is placed on its own line.
+If \"wrap\", the units are wrapped at `wrap_column`.\
+                    ",
+                kind: ConfigKind::OneOf(&["wrap", "one_per_line"]),
+                default: format!("{:?}", defaults.uses_layout).to_lowercase(),
+            },
+            ConfigItem {
+                name: "parameter_layout",
+                description: "\
+The layout of the parameters of routine headers.
+If \"one_per_line_if_wrapped\", each parameter group is placed on its own line
+if they don't all fit on one line.
+If \"one_per_line\", each parameter group is always placed on its own line.
+If \"bin_pack\", the parameters are wrapped at `wrap_column`.\
+                    ",
+                kind: ConfigKind::OneOf(&["bin_pack", "one_per_line", "one_per_line_if_wrapped"]),
+                default: format!("{:?}", defaults.parameter_layout).to_lowercase(),
+            },
+            ConfigItem {
+                name: "argument_layout",
+                description: "\
+The layout of the arguments of routine invocations.
+If \"one_per_line_break_close\", the arguments are all placed on one line or
+each on its own line, and the closing bracket is placed on its own line,
+except within expressions.
+If \"one_per_line\", the arguments are all placed on one line or each on its
+own line, and the closing bracket is placed after the last argument.
+If \"bin_pack\", the arguments are wrapped at `wrap_column`.\
+                    ",
+                kind: ConfigKind::OneOf(&["bin_pack", "one_per_line", "one_per_line_break_close"]),
+                default: format!("{:?}", defaults.argument_layout).to_lowercase(),
+            },
+            ConfigItem {
+                name: "operator_position",
+                description: "\
+Where binary operators (e.g. `+`, `and`) are placed when an expression is
+wrapped.
+If \"auto\", binary operators start the continuation line and the `:=` of an
+assignment ends the line before it.
+If \"before\", binary operators and `:=` start the continuation line.
+If \"after\", binary operators and `:=` end the line before it.\
+                    ",
+                kind: ConfigKind::OneOf(&["auto", "before", "after"]),
+                default: format!("{:?}", defaults.operator_position).to_lowercase(),
+            },
+            ConfigItem {
+                name: "allow_single_line_statements",
+                description: "\
+Whether the single statement of an `if`, `for`, `while`, `with` or `on` clause
+can be placed on the same line as the `then` or `do` before it when it fits
+within `wrap_column` (e.g. `if A then Exit;`). Statements with an `else` or a
+`begin` are always wrapped.\
+                    ",
+                kind: ConfigKind::Boolean,
+                default: defaults.allow_single_line_statements.to_string(),
+            },
             ConfigItem {
                 name: "format_multiline_strings",
                 description: "\
@@ -239,9 +652,149 @@ When enabled, the leading whitespace of internal lines will be rewritten to
 match that of the starting quote, and internal line terminators will be
 normalised. Trailing whitespace is preserved, however.\
                     ",
-                hint: "<boolean>",
+                kind: ConfigKind::Boolean,
                 default: defaults.format_multiline_strings.to_string(),
             },
+            ConfigItem {
+                name: "iteration_max",
+                description: "\
+The maximum number of iterations spent searching for the optimal wrapping of a
+single line. Lines which exceed this are formatted using the
+`iteration_limit_fallback`, and are reported as a warning.\
+                    ",
+                kind: ConfigKind::unsigned(u32::MAX),
+                default: defaults.iteration_max.to_string(),
+            },
+            ConfigItem {
+                name: "iteration_limit_fallback",
+                description: "\
+How to format a line when the search for its optimal wrapping exceeds
+`iteration_max`.
+If \"greedy\", the line is wrapped by repeatedly choosing the locally best
+decision. If that fails, the original line breaks are kept.
+If \"original\", the original line breaks are kept, and continued lines are
+indented by a single continuation.\
+                    ",
+                kind: ConfigKind::OneOf(&["greedy", "original"]),
+                default: format!("{:?}", defaults.iteration_limit_fallback).to_lowercase(),
+            },
+            ConfigItem {
+                name: "keyword_case",
+                description: "\
+The casing of reserved words (e.g. `begin`).
+If \"pascal\", each word within the keyword is capitalised (e.g. `ResourceString`).
+If \"preserve\", the casing is left as it is.\
+                    ",
+                kind: ConfigKind::OneOf(&["lower", "upper", "pascal", "preserve"]),
+                default: format!("{:?}", defaults.keyword_case).to_lowercase(),
+            },
+            ConfigItem {
+                name: "impure_keyword_case",
+                description: "\
+The casing of keywords which are only reserved in certain contexts, such as
+directives (e.g. `override`, `stdcall`, `absolute`). Accepts the same values
+as `keyword_case`.\
+                    ",
+                kind: ConfigKind::OneOf(&["lower", "upper", "pascal", "preserve"]),
+                default: format!("{:?}", defaults.impure_keyword_case).to_lowercase(),
+            },
+            ConfigItem {
+                name: "directive_case",
+                description: "\
+The casing of compiler directive names (e.g. `IFDEF` in `{$IFDEF DEBUG}`, or
+`O` in `{$O+}`).\
+                    ",
+                kind: ConfigKind::OneOf(&["lower", "upper", "preserve"]),
+                default: format!("{:?}", defaults.directive_case).to_lowercase(),
+            },
+            ConfigItem {
+                name: "directive_argument_case",
+                description: "\
+The casing of compiler directive arguments (e.g. `DEBUG` in `{$IFDEF DEBUG}`).
+Quoted strings are never changed, but unquoted file names are.\
+                    ",
+                kind: ConfigKind::OneOf(&["lower", "upper", "preserve"]),
+                default: format!("{:?}", defaults.directive_argument_case).to_lowercase(),
+            },
+            ConfigItem {
+                name: "casing_dictionary",
+                description: "\
+Word lists with the canonical casing of identifiers (e.g. `TStringList`). Each
+identifier which matches a word, ignoring case, is rewritten to that casing.
+Each list is either \"builtin\", for a list of common RTL and VCL identifiers,
+or the path of a file with one word per line. Relative paths are resolved from
+the directory of the configuration file if they are set in it, otherwise from the
+working directory. Words in later lists take precedence.\
+                    ",
+                kind: ConfigKind::StringList,
+                default: format!("{:?}", defaults.casing_dictionary),
+            },
+            ConfigItem {
+                name: "align_declarations",
+                description: "\
+Whether to align the `:`, `=` or `:=` of consecutive declarations in a `var`,
+`const` or `type` section, record, class field block, or run of inline
+declarations. Blank lines and comments start a new group of declarations.
+Declarations which are wrapped before the aligned token are not aligned.\
+                    ",
+                kind: ConfigKind::Boolean,
+                default: defaults.align_declarations.to_string(),
+            },
+            ConfigItem {
+                name: "align_trailing_comments",
+                description: "\
+Whether to align the comments at the end of consecutive lines to a common
+column, which is at most `wrap_column`. Blank lines and changes of indentation
+start a new group of comments.\
+                    ",
+                kind: ConfigKind::Boolean,
+                default: defaults.align_trailing_comments.to_string(),
+            },
+            ConfigItem {
+                name: "max_blank_lines",
+                description: "The largest number of consecutive blank lines.",
+                kind: ConfigKind::unsigned(u16::MAX as u32),
+                default: defaults.max_blank_lines.to_string(),
+            },
+            ConfigItem {
+                name: "blank_lines_between_routines",
+                description: "\
+The least number of blank lines between routine implementations, which are added
+before any comments on the routine. This takes precedence over
+`max_blank_lines`.\
+                    ",
+                kind: ConfigKind::unsigned(u16::MAX as u32),
+                default: defaults.blank_lines_between_routines.to_string(),
+            },
+            ConfigItem {
+                name: "blank_lines_before_sections",
+                description: "\
+The least number of blank lines before the `implementation`, `initialization`
+and `finalization` sections. This takes precedence over `max_blank_lines`.\
+                    ",
+                kind: ConfigKind::unsigned(u16::MAX as u32),
+                default: defaults.blank_lines_before_sections.to_string(),
+            },
+            ConfigItem {
+                name: "trim_blank_lines_in_blocks",
+                description: "\
+Whether to remove blank lines right after `begin` and right before any `end`,
+including the `end` of a `case` statement or a class or record declaration.\
+                    ",
+                kind: ConfigKind::Boolean,
+                default: defaults.trim_blank_lines_in_blocks.to_string(),
+            },
+            ConfigItem {
+                name: "uses_group_order",
+                description: "\
+The order of the groups of units in `uses` clauses, when they are sorted by
+`rules.sort_uses`. Each group is a namespace prefix (e.g. \"System\" for
+`System.SysUtils`), with units in the group with the longest matching prefix.
+\"*\" is the group of units without a matching prefix, which is otherwise last.\
+                    ",
+                kind: ConfigKind::StringList,
+                default: format!("{:?}", defaults.uses_group_order),
+            },
             ConfigItem {
                 name: "encoding",
                 description: "\
@@ -252,19 +805,22 @@ If \"native\":
 
 In all cases a detected BOM will override the configured encoding.\
                     ",
-                hint: "native | <NAME>",
+                kind: ConfigKind::OneOfOrNamed {
+                    values: &["native"],
+                    name: "NAME",
+                },
                 default: format!("{:?}", defaults.encoding).to_lowercase(),
             },
             ConfigItem {
                 name: "use_tabs",
                 description: "Use tab characters for indentation",
-                hint: "<boolean>",
+                kind: ConfigKind::Boolean,
                 default: defaults.use_tabs.to_string(),
             },
             ConfigItem {
                 name: "tab_width",
                 description: "Number of spaces per indentation (ignored if use_tabs=true)",
-                hint: "<unsigned integer>",
+                kind: ConfigKind::unsigned(u8::MAX as u32),
                 default: defaults.tab_width.to_string(),
             },
             ConfigItem {
@@ -274,7 +830,7 @@ Width of continuations, measured as a multiple of the configured indentation.
 Continuations are used to further indent the wrapped lines from a \"logical line\".
 Indentations are used to indent the base of a \"logical line\".
 ",
-                hint: "<unsigned integer>",
+                kind: ConfigKind::unsigned(u8::MAX as u32),
                 default: defaults.continuation_indents.to_string(),
             },
             ConfigItem {
@@ -285,15 +841,127 @@ If \"native\":
   * on Windows, \"crlf\" is used
   * otherwise, \"lf\" is used\
                     ",
-                hint: "[ lf | crlf | native ]",
+                kind: ConfigKind::OneOfWithAliases {
+                    values: LineEnding::VALUES,
+                    aliases: LineEnding::ALIASES,
+                },
                 default: format!("{:?}", defaults.line_ending).to_lowercase(),
             },
+            ConfigItem {
+                name: "rules.keyword_case",
+                description: "Whether to apply `keyword_case` and `impure_keyword_case`",
+                kind: ConfigKind::Boolean,
+                default: defaults.rules.keyword_case.to_string(),
+            },
+            ConfigItem {
+                name: "rules.identifier_case",
+                description: "\
+Whether to rewrite each identifier to the casing of its first declaration in
+the same file, and the implicit `Result` variable of functions to `Result`.\
+                    ",
+                kind: ConfigKind::Boolean,
+                default: defaults.rules.identifier_case.to_string(),
+            },
+            ConfigItem {
+                name: "rules.sort_uses",
+                description: "\
+Whether to sort the units in `uses` clauses alphabetically, in the groups of
+`uses_group_order`. Comments stay with their units, and clauses containing
+compiler directives (e.g. `{$IFDEF}`) are not sorted.\
+                    ",
+                kind: ConfigKind::Boolean,
+                default: defaults.rules.sort_uses.to_string(),
+            },
+            ConfigItem {
+                name: "rules.comment_contents",
+                description: "\
+Whether to format the contents of line comments.
+When enabled, a space is inserted after the `//` and trailing whitespace is
+removed.\
+                    ",
+                kind: ConfigKind::Boolean,
+                default: defaults.rules.comment_contents.to_string(),
+            },
+            ConfigItem {
+                name: "rules.directive_case",
+                description: "Whether to apply `directive_case` and `directive_argument_case`",
+                kind: ConfigKind::Boolean,
+                default: defaults.rules.directive_case.to_string(),
+            },
+            ConfigItem {
+                name: "rules.eof_newline",
+                description: "Whether to end files with a line ending",
+                kind: ConfigKind::Boolean,
+                default: defaults.rules.eof_newline.to_string(),
+            },
         ]
     }
+
+    fn infer(samples: &[SourceSample]) -> toml::Table {
+        let style = SourceStyle::from_samples(samples);
+
+        let mut inferred = toml::Table::new();
+        if let Some(use_tabs) = style.use_tabs() {
+            inferred.insert("use_tabs".to_owned(), use_tabs.into());
+            if let (false, Some(tab_width)) = (use_tabs, style.tab_width()) {
+                inferred.insert("tab_width".to_owned(), i64::from(tab_width).into());
+            }
+        }
+        if let Some(line_ending) = style.line_ending() {
+            inferred.insert("line_ending".to_owned(), line_ending.into());
+        }
+        if let Some(encoding) = style.encoding() {
+            inferred.insert("encoding".to_owned(), encoding.into());
+        }
+        inferred
+    }
 }
 
 pub fn format(config: PasFmtConfiguration<FormattingConfig>, err_handler: impl ErrHandler) {
-    let formatting_settings = match config.get_config_object() {
+    if let Some(PasFmtCommand::Init(options)) = config.command() {
+        match init_config_file::<FormattingConfig>(options) {
+            Ok(path) => println!("Wrote {}", path.display()),
+            Err(e) => err_handler(e),
+        }
+        return;
+    }
+
+    if config.config_schema_requested() {
+        match PasFmtConfiguration::<FormattingConfig>::config_schema() {
+            Ok(schema) => print!("{schema}"),
+            Err(e) => err_handler(e),
+        }
+        return;
+    }
+
+    if config.config_show_requested() {
+        match config.show_config() {
+            Ok(shown) => print!("{shown}"),
+            Err(e) => err_handler(e),
+        }
+        return;
+    }
+
+    let resolver = match config.get_config_resolver() {
+        Ok(resolver) => resolver,
+        Err(e) => {
+            err_handler(e);
+            return;
+        }
+    };
+    let base_dir = match casing_dictionary_base_dir(&config) {
+        Ok(base_dir) => base_dir,
+        Err(e) => {
+            err_handler(e);
+            return;
+        }
+    };
+    let resolve = move |settings: &[InlineSetting]| -> anyhow::Result<FormattingConfig> {
+        let mut formatting_settings = resolver.resolve(settings)?;
+        formatting_settings.resolve_paths(&base_dir);
+        Ok(formatting_settings)
+    };
+    let formatting_settings = match resolve(&[]) {
         Ok(formatting_settings) => formatting_settings,
         Err(e) => {
             err_handler(e);
@@ -303,39 +971,149 @@ pub fn format(config: PasFmtConfiguration<FormattingConfig>, err_handler: impl E
     log::debug!("Configuration:\n{:#?}", formatting_settings);
 
     let encoding: &'static Encoding = formatting_settings.encoding.into();
-    let formatter = make_formatter(&formatting_settings);
-    let file_formatter = FileFormatter::new(formatter, encoding);
-    FormattingOrchestrator::run(file_formatter, config, err_handler)
+    let explain = config.explain();
+    let explained = Arc::new(AtomicBool::new(false));
+    let formatter_for = {
+        let explained = explained.clone();
+        move |formatting_settings: &FormattingConfig| {
+            let Some(line_number) = explain else {
+                return make_formatter(formatting_settings);
+            };
+            let explained = explained.clone();
+            let olf = OptimisingLineFormatter::new(
+                formatting_settings.into(),
+                formatting_settings.into(),
+            )
+            .with_explanation(line_number, move |explanation| {
+                explained.store(true, Ordering::Relaxed);
+                print!("{explanation}");
+            });
+            build_formatter(formatting_settings, olf)
+        }
+    };
+
+    let formatter = match formatter_for(&formatting_settings) {
+        Ok(formatter) => formatter,
+        Err(e) => {
+            err_handler(e);
+            return;
+        }
+    };
+    let file_formatter =
+        FileFormatter::new(formatter, encoding).with_inline_settings(move |settings| {
+            if let Some(setting) = settings
+                .iter()
+                .find(|setting| matches!(&*setting.key, "encoding" | "casing_dictionary"))
+            {
+                anyhow::bail!("`{}` cannot be set for an individual file", setting.key);
+            }
+            let formatting_settings = resolve(settings)?;
+            log::debug!("Inline configuration:\n{:#?}", formatting_settings);
+            formatter_for(&formatting_settings)
+        });
+
+    let had_error = AtomicBool::new(false);
+    FormattingOrchestrator::run(file_formatter, config, |e| {
+        had_error.store(true, Ordering::Relaxed);
+        err_handler(e);
+    });
+    if let Some(line_number) = explain
+        && !had_error.into_inner()
+        && !explained.load(Ordering::Relaxed)
+    {
+        err_handler(anyhow::anyhow!(
+            "line {line_number} does not contain any code to explain"
+        ));
+    }
 }
 
-pub fn make_formatter(config: &FormattingConfig) -> Formatter {
+/// The directory that the relative paths of `casing_dictionary` are resolved
+/// from: the directory of the configuration file if they come from it,
+/// otherwise the working directory.
+fn casing_dictionary_base_dir(
+    config: &PasFmtConfiguration<FormattingConfig>,
+) -> anyhow::Result<PathBuf> {
+    if config.is_set_by_config_file("casing_dictionary")?
+        && let Some(file) = config.get_config_file()?
+    {
+        return Ok(file.parent().map(Path::to_path_buf).unwrap_or_default());
+    }
+    Ok(std::env::current_dir()?)
+}
+
+pub fn make_formatter(config: &FormattingConfig) -> anyhow::Result<Formatter> {
+    build_formatter(
+        config,
+        OptimisingLineFormatter::new(config.into(), config.into()),
+    )
+}
+
+fn build_formatter(
+    config: &FormattingConfig,
+    olf: OptimisingLineFormatter,
+) -> anyhow::Result<Formatter> {
     let reconstruction_settings: ReconstructionSettings = config.into();
+    let rules = config.rules;
 
     let eof_newline_formatter = &EofNewline {};
 
-    Formatter::builder()
-        .lexer(DelphiLexer {})
-        .parser(DelphiLogicalLineParser {})
+    let builder = Formatter::builder().lexer(DelphiLexer {});
+    let builder = if rules.sort_uses {
+        builder
+            .raw_token_consolidator(SortUses::from(config))
+            .parser(DelphiLogicalLineParser {})
+    } else {
+        builder.parser(DelphiLogicalLineParser {})
+    };
+    let mut builder = builder
         .token_consolidator(DistinguishGenericTypeParamsConsolidator {})
         .lines_consolidator(ConditionalDirectiveConsolidator {})
         .lines_consolidator(DeindentPackageDirectives {})
         .token_ignorer(FormattingToggler {})
         .token_ignorer(IgnoreAsmIstructions {})
-        .file_formatter(TokenSpacing {})
-        .file_formatter(LowercaseKeywords {})
-        .file_formatter(CommentFormatter {})
-        .line_formatter(FormatterSelector::new(
-            |logical_line_type| match logical_line_type {
+        .file_formatter(TokenSpacing {});
+    if rules.keyword_case {
+        builder = builder.file_formatter(KeywordCase::from(config));
+    }
+    if rules.identifier_case {
+        builder = builder.file_formatter(IdentifierCase {});
+    }
+    if let Some(dictionary) = config.casing_dictionary()? {
+        builder = builder.file_formatter(dictionary);
+    }
+    if rules.comment_contents {
+        builder = builder.file_formatter(CommentFormatter {});
+    }
+    if rules.directive_case {
+        builder = builder.file_formatter(DirectiveCase::from(config));
+    }
+    if rules.eof_newline {
+        builder = builder.line_formatter(FormatterSelector::new(|logical_line_type| {
+            match logical_line_type {
                 LogicalLineType::Eof => Some(eof_newline_formatter),
                 _ => None,
-            },
-        ))
-        .file_formatter(OptimisingLineFormatter::new(
-            config.into(),
+            }
+        }));
+    }
+    if config.align_declarations {
+        builder = builder.file_formatter(AlignDeclarations::new(
+            config.wrap_column,
+            config.max_blank_lines,
             reconstruction_settings.clone(),
-        ))
+        ));
+    }
+    builder = builder
+        .file_formatter(olf)
+        .file_formatter(BlankLines::from(config));
+    if config.align_trailing_comments {
+        builder = builder.file_formatter(AlignTrailingComments::new(
+            config.wrap_column,
+            reconstruction_settings.clone(),
+        ));
+    }
+    Ok(builder
         .reconstructor(DelphiLogicalLinesReconstructor::new(
             reconstruction_settings,
         ))
-        .build()
+        .build())
 }
@@ -81,3 +81,472 @@ fn config_file_found_in_parent_dir() -> TestResult {
 
     Ok(())
 }
+
+#[test]
+fn line_ending_accepts_uppercase_aliases() -> TestResult {
+    pasfmt()?
+        .args(["-C", "line_ending=CRLF"])
+        .write_stdin("a;\nb;")
+        .assert()
+        .success()
+        .stdout(predicate::eq("a;\r\nb;\r\n"));
+
+    pasfmt()?
+        .args(["-C", "line_ending=Lf"])
+        .write_stdin("a;")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "unknown line ending `Lf`, expected one of `lf`, `crlf`, `native`",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn show_config_names_value_sources() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    let config = tmp.child("pasfmt.toml");
+    config.write_str("wrap_column = 100\ntab_width = 4")?;
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .args(["-C", "show", "-C", "tab_width=8"])
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!(
+            "# config file: {}\nwrap_column = 100 # from {0}\n",
+            config.display()
+        )))
+        .stdout(predicate::str::contains(
+            "begin_style = \"auto\" # default\n",
+        ))
+        .stdout(predicate::str::contains(
+            "tab_width = 8 # from -C tab_width=8\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn schema_is_up_to_date() -> TestResult {
+    // To update, run `pasfmt -C schema > docs/pasfmt.schema.json`
+    pasfmt()?
+        .args(["-C", "schema"])
+        .assert()
+        .success()
+        .stdout(predicate::eq(include_str!("../../docs/pasfmt.schema.json")));
+
+    Ok(())
+}
+
+#[test]
+fn rules_can_be_disabled() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    tmp.child("pasfmt.toml")
+        .write_str("[rules]\nkeyword_case = false\ndirective_case = false")?;
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .write_stdin("BEGIN {$define foo}//a\nEND.")
+        .assert()
+        .success()
+        .stdout("BEGIN\n  {$define foo} // a\nEND.\n");
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .args([
+            "-C",
+            "rules.comment_contents=false",
+            "-C",
+            "rules.eof_newline=false",
+        ])
+        .write_stdin("BEGIN {$define foo}//a\nEND.")
+        .assert()
+        .success()
+        .stdout("BEGIN\n  {$define foo} //a\nEND.");
+
+    Ok(())
+}
+
+#[test]
+fn keyword_case_is_separate_for_impure_keywords() -> TestResult {
+    pasfmt()?
+        .args([
+            "-C",
+            "keyword_case=upper",
+            "-C",
+            "impure_keyword_case=pascal",
+        ])
+        .write_stdin("procedure Foo; stdcall; begin end;")
+        .assert()
+        .success()
+        .stdout("PROCEDURE Foo; StdCall;\nBEGIN\nEND;\n");
+
+    Ok(())
+}
+
+#[test]
+fn directive_case_is_separate_for_arguments() -> TestResult {
+    pasfmt()?
+        .args([
+            "-C",
+            "directive_case=lower",
+            "-C",
+            "directive_argument_case=upper",
+        ])
+        .write_stdin("{$IFDEF Debug}{$MESSAGE 'Debug'}{$ENDIF}")
+        .assert()
+        .success()
+        .stdout("{$ifdef DEBUG}\n  {$message 'Debug'}\n{$endif}\n");
+
+    Ok(())
+}
+
+#[test]
+fn identifier_case_is_opt_in() -> TestResult {
+    let input = "var\n  FooBar: Integer;\nbegin\n  foobar := 1;\nend.\n";
+
+    pasfmt()?
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout(input);
+
+    pasfmt()?
+        .args(["-C", "rules.identifier_case=true"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .stdout("var\n  FooBar: Integer;\nbegin\n  FooBar := 1;\nend.\n");
+
+    Ok(())
+}
+
+#[test]
+fn casing_dictionary_paths_are_relative_to_config_file() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    tmp.child("words.txt").write_str("# ours\nMyHelper\n")?;
+    tmp.child("pasfmt.toml")
+        .write_str("casing_dictionary = [\"builtin\", \"words.txt\"]")?;
+    tmp.child("src").create_dir_all()?;
+
+    pasfmt()?
+        .current_dir(tmp.child("src").path())
+        .write_stdin("myhelper(x);\nfreeandnil(x);")
+        .assert()
+        .success()
+        .stdout("MyHelper(x);\nFreeAndNil(x);\n");
+
+    Ok(())
+}
+
+#[test]
+fn overridden_casing_dictionary_paths_are_relative_to_working_dir() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    tmp.child("pasfmt.toml")
+        .write_str("casing_dictionary = []")?;
+    tmp.child("src/words.txt").write_str("MyHelper\n")?;
+
+    pasfmt()?
+        .current_dir(tmp.child("src").path())
+        .args(["-C", "casing_dictionary=words.txt"])
+        .write_stdin("myhelper(x);")
+        .assert()
+        .success()
+        .stdout("MyHelper(x);\n");
+
+    pasfmt()?
+        .current_dir(tmp.child("src").path())
+        .env("PASFMT_CASING_DICTIONARY", "words.txt")
+        .write_stdin("myhelper(x);")
+        .assert()
+        .success()
+        .stdout("MyHelper(x);\n");
+
+    Ok(())
+}
+
+#[test]
+fn missing_casing_dictionary_raises_error() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .args(["-C", "casing_dictionary=words.txt"])
+        .write_stdin("x;")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("failed to read casing dictionary"));
+
+    Ok(())
+}
+
+#[test]
+fn declarations_can_be_aligned() -> TestResult {
+    pasfmt()?
+        .args(["-C", "align_declarations=true"])
+        .write_stdin("var\n  A: Integer;\n  LongName: string;\nconst\n  B = 1;\n  CC = 2;")
+        .assert()
+        .success()
+        .stdout("var\n  A       : Integer;\n  LongName: string;\nconst\n  B  = 1;\n  CC = 2;\n");
+
+    Ok(())
+}
+
+#[test]
+fn trailing_comments_can_be_aligned() -> TestResult {
+    pasfmt()?
+        .args(["-C", "align_trailing_comments=true"])
+        .write_stdin("begin\n  A; // a\n  Bbb; // b\nend.")
+        .assert()
+        .success()
+        .stdout("begin\n  A;   // a\n  Bbb; // b\nend.\n");
+
+    Ok(())
+}
+
+#[test]
+fn blank_lines_can_be_configured() -> TestResult {
+    pasfmt()?
+        .write_stdin("a;\n\n\n\nb;")
+        .assert()
+        .success()
+        .stdout("a;\n\nb;\n");
+
+    pasfmt()?
+        .args([
+            "-C",
+            "max_blank_lines=0",
+            "-C",
+            "blank_lines_between_routines=1",
+        ])
+        .write_stdin("procedure A;\nbegin\nend;\nprocedure B;\nbegin\n\n  a;\nend;")
+        .assert()
+        .success()
+        .stdout("procedure A;\nbegin\nend;\n\nprocedure B;\nbegin\n  a;\nend;\n");
+
+    Ok(())
+}
+
+#[test]
+fn uses_clauses_can_be_sorted() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    tmp.child("pasfmt.toml")
+        .write_str("uses_group_order = [\"Vcl\", \"*\", \"System\"]\n[rules]\nsort_uses = true")?;
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .write_stdin("uses System.Classes, b, Vcl.Forms, A;")
+        .assert()
+        .success()
+        .stdout("uses\n  Vcl.Forms,\n  A,\n  b,\n  System.Classes;\n");
+
+    Ok(())
+}
+
+#[test]
+fn uses_clauses_can_be_wrapped() -> TestResult {
+    pasfmt()?
+        .args(["-C", "uses_layout=wrap"])
+        .write_stdin("uses A, B, C;")
+        .assert()
+        .success()
+        .stdout("uses\n  A, B, C;\n");
+
+    Ok(())
+}
+
+#[test]
+fn parameter_layout_can_be_configured() -> TestResult {
+    pasfmt()?
+        .args(["-C", "parameter_layout=one_per_line"])
+        .write_stdin("procedure A(B: C; D: E);")
+        .assert()
+        .success()
+        .stdout("procedure A(\n    B: C;\n    D: E\n);\n");
+
+    pasfmt()?
+        .args(["-C", "wrap_column=30", "-C", "parameter_layout=bin_pack"])
+        .write_stdin("procedure A(BBBB: C; DDDD: E; FFFF: G);")
+        .assert()
+        .success()
+        .stdout("procedure A(\n    BBBB: C; DDDD: E; FFFF: G\n);\n");
+
+    Ok(())
+}
+
+#[test]
+fn argument_layout_can_be_configured() -> TestResult {
+    pasfmt()?
+        .args(["-C", "wrap_column=20", "-C", "argument_layout=one_per_line"])
+        .write_stdin("Foo(Aaaa, Bbbb, Cccc);")
+        .assert()
+        .success()
+        .stdout("Foo(\n    Aaaa,\n    Bbbb,\n    Cccc);\n");
+
+    pasfmt()?
+        .args(["-C", "wrap_column=20", "-C", "argument_layout=bin_pack"])
+        .write_stdin("Foo(Aaaa, Bbbb, Cccc);")
+        .assert()
+        .success()
+        .stdout("Foo(\n    Aaaa, Bbbb, Cccc\n);\n");
+
+    Ok(())
+}
+
+#[test]
+fn operators_can_end_lines() -> TestResult {
+    pasfmt()?
+        .args(["-C", "wrap_column=20", "-C", "operator_position=after"])
+        .write_stdin("A := Bbbbbbbb + Cccccccc;")
+        .assert()
+        .success()
+        .stdout("A :=\n    Bbbbbbbb +\n        Cccccccc;\n");
+
+    Ok(())
+}
+
+#[test]
+fn assignments_can_start_lines() -> TestResult {
+    pasfmt()?
+        .args(["-C", "wrap_column=20", "-C", "operator_position=before"])
+        .write_stdin("Aaaaaaaaaa := Bbbbbbbb + Cccccccc;")
+        .assert()
+        .success()
+        .stdout("Aaaaaaaaaa\n    := Bbbbbbbb\n        + Cccccccc;\n");
+
+    Ok(())
+}
+
+#[test]
+fn else_can_be_cuddled() -> TestResult {
+    pasfmt()?
+        .args(["-C", "begin_style=cuddle_else"])
+        .write_stdin("if A then begin\n  B;\nend\nelse begin\n  C;\nend;")
+        .assert()
+        .success()
+        .stdout("if A then begin\n  B;\nend else begin\n  C;\nend;\n");
+
+    Ok(())
+}
+
+#[test]
+fn single_line_statements_can_be_allowed() -> TestResult {
+    pasfmt()?
+        .args(["-C", "allow_single_line_statements=true"])
+        .write_stdin("if A then\n  Exit;\nfor I := 0 to N do\n  Inc(T);")
+        .assert()
+        .success()
+        .stdout("if A then Exit;\nfor I := 0 to N do Inc(T);\n");
+
+    Ok(())
+}
+
+#[test]
+fn empty_rules_table_is_allowed() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    tmp.child("pasfmt.toml").write_str("[rules]\n")?;
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .write_stdin("BEGIN END.")
+        .assert()
+        .success()
+        .stdout("begin\nend.\n");
+
+    Ok(())
+}
+
+#[test]
+fn env_vars_override_config_file() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    let config = tmp.child("custom.toml");
+    config.write_str("wrap_column = 100\ntab_width = 4")?;
+
+    pasfmt()?
+        .env("PASFMT_CONFIG", config.path())
+        .env("PASFMT_TAB_WIDTH", "8")
+        .args(["-C", "show", "-C", "wrap_column=90"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "# config file: {}\n",
+            config.display()
+        )))
+        .stdout(predicate::str::contains(
+            "wrap_column = 90 # from -C wrap_column=90\n",
+        ))
+        .stdout(predicate::str::contains(
+            "tab_width = 8 # from PASFMT_TAB_WIDTH=8\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn inline_settings_override_config_file() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    tmp.child("pasfmt.toml").write_str("wrap_column = 100")?;
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .write_stdin("// pasfmt: wrap_column=20\nfoo(aaaa, bbbb, cccc);")
+        .assert()
+        .success()
+        .stdout("// pasfmt: wrap_column=20\nfoo(\n    aaaa,\n    bbbb,\n    cccc\n);\n");
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .arg("-Cwrap_column=100")
+        .write_stdin("// pasfmt: wrap_column=20\nfoo(aaaa, bbbb, cccc);")
+        .assert()
+        .success()
+        .stdout("// pasfmt: wrap_column=20\nfoo(aaaa, bbbb, cccc);\n");
+
+    Ok(())
+}
+
+#[test]
+fn invalid_inline_settings_raise_error() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    let file = tmp.child("foo.pas");
+    file.write_str("// pasfmt: asdf=0\na ;")?;
+
+    pasfmt()?
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "ERROR invalid `pasfmt:` settings in '{}'",
+            file.display()
+        )))
+        .stderr(predicate::str::contains("unknown field `asdf`, expected "));
+    file.assert("// pasfmt: asdf=0\na ;");
+
+    Ok(())
+}
+
+#[test]
+fn inline_casing_dictionary_raises_error() -> TestResult {
+    let tmp = assert_fs::TempDir::new()?;
+    tmp.child("words.txt").write_str("FOO\n")?;
+    let file = tmp.child("foo.pas");
+    file.write_str("// pasfmt: casing_dictionary=[\"words.txt\"]\nfoo;")?;
+
+    pasfmt()?
+        .current_dir(tmp.path())
+        .arg(file.path())
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(format!(
+            "ERROR invalid `pasfmt:` settings in '{}'",
+            file.display()
+        )))
+        .stderr(predicate::str::contains(
+            "`casing_dictionary` cannot be set for an individual file",
+        ));
+    file.assert("// pasfmt: casing_dictionary=[\"words.txt\"]\nfoo;");
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use assert_fs::{TempDir, prelude::*};
+use predicates::prelude::*;
+use std::fs::read_to_string;
+
+use crate::utils::*;
+
+#[test]
+fn dump_stdin() -> TestResult {
+    pasfmt()?
+        .arg("--dump=tokens")
+        .write_stdin("a ;")
+        .assert()
+        .success()
+        .stdout(predicate::eq(
+            "0: Identifier \"a\" (whitespace: \"\")\n\
+            1: Op(Semicolon) \";\" (whitespace: \" \")\n\
+            2: Eof \"\" (whitespace: \"\")\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn dump_file_does_not_format() -> TestResult {
+    let tmp = TempDir::new()?;
+
+    let child = tmp.child("foo.pas");
+    child.write_str("a ;")?;
+
+    pasfmt()?
+        .arg("--dump=lines")
+        .arg(&*child)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!(
+            "{}:\n0: Unknown (level: 0) [0, 1] a ;\n",
+            child.display()
+        )));
+
+    assert_eq!(read_to_string(child)?, "a ;");
+
+    Ok(())
+}
+
+#[test]
+fn dump_json_is_one_object_per_file() -> TestResult {
+    let tmp = TempDir::new()?;
+
+    tmp.child("foo.pas").write_str("a;")?;
+    tmp.child("bar.pas").write_str("b;")?;
+
+    let output = pasfmt()?
+        .arg("--dump=contexts")
+        .arg("--dump-format=json")
+        .arg(&*tmp)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let output = String::from_utf8(output)?;
+    assert_eq!(output.lines().count(), 2);
+    for line in output.lines() {
+        assert!(
+            line.starts_with(r#"{"contexts":[{"#),
+            "unexpected line: {line}"
+        );
+        assert!(line.contains(r#""path":"#), "unexpected line: {line}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn dump_files_in_argument_order() -> TestResult {
+    let tmp = TempDir::new()?;
+
+    let children: Vec<_> = (0..16)
+        .map(|i| {
+            let child = tmp.child(format!("{i}.pas"));
+            child.write_str(&format!("a{i};"))?;
+            Ok(child)
+        })
+        .collect::<Result<_, assert_fs::fixture::FixtureError>>()?;
+
+    let expected: String = children
+        .iter()
+        .enumerate()
+        .map(|(i, child)| {
+            format!(
+                "{}:\n0: Unknown (level: 0) [0, 1] a{i} ;\n1: Eof (level: 0) [2]\n\n",
+                child.display()
+            )
+        })
+        .collect();
+
+    pasfmt()?
+        .arg("--dump=lines")
+        .args(children.iter().map(|child| child.path()))
+        .assert()
+        .success()
+        .stdout(predicate::eq(expected));
+
+    Ok(())
+}
+
+#[test]
+fn dump_conflicts_with_mode() -> TestResult {
+    pasfmt()?
+        .arg("--dump=tokens")
+        .arg("--mode=check")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "the argument '--dump <STAGE>' cannot be used with '--mode <MODE>'",
+        ));
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use assert_fs::{TempDir, prelude::*};
+use predicates::prelude::*;
+use std::fs::read_to_string;
+
+use crate::utils::*;
+
+#[test]
+fn explain_stdin() -> TestResult {
+    pasfmt()?
+        .arg("--explain=2")
+        .arg("-Cwrap_column=20")
+        .write_stdin("begin\n  Result := Foo(A, B + C);\nend.\n")
+        .assert()
+        .success()
+        .stdout(
+            predicate::str::starts_with(
+                "Line 2 is part of the `Assignment` logical line 1:\n  Result := Foo(A, B + C);\n",
+            )
+            .and(predicate::str::contains("AssignRHS [3..10]"))
+            .and(predicate::str::contains("(iteration_max: 20000)\n"))
+            .and(predicate::str::contains(
+                "  1 \"Result\": MustBreak (forced) -> Break { continuations: 0 }\n",
+            ))
+            .and(predicate::str::contains("Chosen solution (penalty "))
+            .and(predicate::str::contains("Runner-up candidates:\n")),
+        );
+
+    Ok(())
+}
+
+#[test]
+fn explain_uses_inline_settings() -> TestResult {
+    pasfmt()?
+        .arg("--explain=2")
+        .write_stdin("// pasfmt: wrap_column=20\nfoo(aaaa, bbbb, cccc);\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  3 \"aaaa\": Indifferent -> Break { continuations: 1 }\n",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn explain_file_does_not_format() -> TestResult {
+    let tmp = TempDir::new()?;
+
+    let child = tmp.child("foo.pas");
+    child.write_str("a  ;")?;
+
+    pasfmt()?
+        .arg("--explain=1")
+        .arg(&*child)
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(
+            "Line 1 is part of the `Unknown` logical line 0:\na  ;\n",
+        ));
+
+    assert_eq!(read_to_string(child)?, "a  ;");
+
+    Ok(())
+}
+
+#[test]
+fn explain_line_without_code() -> TestResult {
+    pasfmt()?
+        .arg("--explain=2")
+        .write_stdin("a;\n\nb;\n")
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains(
+            "line 2 does not contain any code to explain",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn explain_requires_one_file() -> TestResult {
+    let tmp = TempDir::new()?;
+
+    tmp.child("foo.pas").write_str("a;")?;
+    tmp.child("bar.pas").write_str("b;")?;
+
+    pasfmt()?
+        .arg("--explain=1")
+        .arg(&*tmp)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "exactly one file can be explained, but 2 were provided",
+        ));
+
+    Ok(())
+}
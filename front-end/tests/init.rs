@@ -0,0 +1,86 @@
+use assert_fs::{TempDir, prelude::*};
+use predicates::prelude::*;
+use std::fs::read_to_string;
+
+use crate::utils::*;
+
+#[test]
+fn init_writes_commented_defaults() -> TestResult {
+    let tmp = TempDir::new()?;
+
+    pasfmt()?.arg("init").arg(&*tmp).assert().success();
+
+    let config = read_to_string(tmp.child("pasfmt.toml"))?;
+    assert!(config.contains("# Target line length before wrapping\n"));
+    assert!(config.contains("\n# wrap_column = 120\n"));
+    assert!(config.contains("\n# line_ending = \"native\"\n"));
+    assert!(config.contains("\n[rules]\n"));
+    assert!(
+        config
+            .lines()
+            .all(|line| line.is_empty() || line.starts_with('#') || line.starts_with('['))
+    );
+
+    // The written file must be usable as-is.
+    pasfmt()?
+        .current_dir(&tmp)
+        .write_stdin("a ;")
+        .assert()
+        .success()
+        .stdout("a;\n");
+
+    Ok(())
+}
+
+#[test]
+fn init_infers_from_sources() -> TestResult {
+    let tmp = TempDir::new()?;
+    tmp.child("src/a.pas")
+        .write_str("begin\r\n\tif a then\r\n\t\tb;\r\nend.\r\n")?;
+
+    pasfmt()?
+        .arg("init")
+        .arg("--infer")
+        .arg(&*tmp)
+        .assert()
+        .success();
+
+    let config = read_to_string(tmp.child("pasfmt.toml"))?;
+    assert!(
+        config.contains("\nuse_tabs = true # inferred\n"),
+        "{config}"
+    );
+    assert!(
+        config.contains("\nline_ending = \"crlf\" # inferred\n"),
+        "{config}"
+    );
+    assert!(config.contains("\n# tab_width = 2\n"), "{config}");
+
+    Ok(())
+}
+
+#[test]
+fn init_does_not_overwrite_without_force() -> TestResult {
+    let tmp = TempDir::new()?;
+    let config = tmp.child("pasfmt.toml");
+    config.write_str("wrap_column = 80")?;
+
+    pasfmt()?
+        .arg("init")
+        .arg(&*tmp)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "already exists, use `--force` to overwrite it",
+        ));
+    assert_eq!(read_to_string(&config)?, "wrap_column = 80");
+
+    pasfmt()?
+        .args(["init", "--force"])
+        .arg(&*tmp)
+        .assert()
+        .success();
+    assert_ne!(read_to_string(&config)?, "wrap_column = 80");
+
+    Ok(())
+}
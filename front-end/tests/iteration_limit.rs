@@ -0,0 +1,87 @@
+use assert_fs::{TempDir, prelude::*};
+use predicates::prelude::*;
+
+use crate::utils::*;
+
+const INPUT: &str = "\
+begin
+  if A then
+    Foo(AAAAAAAAAA, BBBBBBBBBBBB,
+  CCCCCCCCCCC)
+  else Bar;
+end.
+";
+
+#[test]
+fn greedy_fallback_is_reported() -> TestResult {
+    let tmp = TempDir::new()?;
+
+    let file = tmp.child("a.pas");
+    file.write_str(INPUT)?;
+
+    pasfmt()?
+        .arg("-Citeration_max=1")
+        .arg("-Cwrap_column=30")
+        .arg(&*file)
+        .assert()
+        .success()
+        .stderr(predicate::str::contains(format!(
+            "{}:2: iteration limit (1) reached, formatted using the `greedy` fallback",
+            file.display()
+        )));
+
+    file.assert(
+        "\
+begin
+  if A then
+    Foo(
+        AAAAAAAAAA,
+        BBBBBBBBBBBB,
+        CCCCCCCCCCC
+    )
+  else
+    Bar;
+end.
+",
+    );
+
+    Ok(())
+}
+
+#[test]
+fn original_fallback_is_reported() -> TestResult {
+    pasfmt()?
+        .arg("-Citeration_max=1")
+        .arg("-Citeration_limit_fallback=original")
+        .arg("-Cwrap_column=30")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stdout(
+            "\
+begin
+  if A then
+    Foo(AAAAAAAAAA, BBBBBBBBBBBB,
+        CCCCCCCCCCC)
+  else Bar;
+end.
+",
+        )
+        .stderr(predicate::str::contains(
+            "<stdin>:2: iteration limit (1) reached, formatted using the `original` fallback",
+        ));
+
+    Ok(())
+}
+
+#[test]
+fn limit_not_reached_is_not_reported() -> TestResult {
+    pasfmt()?
+        .arg("-Cwrap_column=30")
+        .write_stdin(INPUT)
+        .assert()
+        .success()
+        .stderr("");
+
+    Ok(())
+}
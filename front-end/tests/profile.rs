@@ -0,0 +1,54 @@
+use assert_fs::{TempDir, prelude::*};
+use std::fs::read_to_string;
+
+use crate::utils::*;
+
+#[test]
+fn profile_contains_file_and_stage_events() -> TestResult {
+    let tmp = TempDir::new()?;
+
+    let child = tmp.child("foo.pas");
+    child.write_str("begin a := b; end.")?;
+    let trace = tmp.child("trace.json");
+
+    pasfmt()?
+        .arg("--profile")
+        .arg(&*trace)
+        .arg(&*child)
+        .assert()
+        .success();
+
+    let trace: serde_json::Value = serde_json::from_str(&read_to_string(trace)?)?;
+    let names: Vec<_> = trace["traceEvents"]
+        .as_array()
+        .expect("traceEvents should be an array")
+        .iter()
+        .map(|event| event["name"].as_str().unwrap_or_default().to_owned())
+        .collect();
+
+    assert!(names.contains(&child.display().to_string()), "{names:?}");
+    for stage in [
+        "lex",
+        "parse",
+        "TokenSpacing",
+        "OptimisingLineFormatter",
+        "reconstruct",
+    ] {
+        assert!(
+            names.iter().any(|name| name == stage),
+            "{stage} missing from {names:?}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn profile_conflicts_with_dump() -> TestResult {
+    pasfmt()?
+        .args(["--profile=trace.json", "--dump=tokens"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
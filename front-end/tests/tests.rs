@@ -2,11 +2,16 @@ mod utils;
 
 mod config;
 mod cursor;
+mod dump;
 mod encoding;
+mod explain;
 mod file_discovery;
 mod help;
 #[cfg(windows)]
 mod idempotence;
+mod init;
 mod io_error;
+mod iteration_limit;
 mod logging;
 mod modes;
+mod profile;
@@ -11,16 +11,20 @@ use anstyle::AnsiColor;
 use anyhow::Context;
 pub use clap::{self, CommandFactory, Parser, error::ErrorKind};
 use clap::{
-    Args, ValueEnum,
+    Args, Subcommand, ValueEnum,
     builder::{PossibleValuesParser, StyledStr, Styles, TypedValueParser},
 };
 
-use config::{Config, File, FileFormat};
-use log::{LevelFilter, debug};
+use config::{Config, Environment, File, FileFormat};
+use log::{LevelFilter, debug, warn};
+use pasfmt_core::prelude::InlineSetting;
+use serde_json::json;
 
-use crate::formatting_orchestrator::FormatterConfiguration;
+use crate::{formatting_orchestrator::FormatterConfiguration, init::SourceSample};
 
-const DEFAULT_CONFIG_FILE_NAME: &str = "pasfmt.toml";
+pub(crate) const DEFAULT_CONFIG_FILE_NAME: &str = "pasfmt.toml";
+const ENV_PREFIX: &str = "PASFMT";
+const ENV_CONFIG_FILE: &str = "PASFMT_CONFIG";
 
 #[derive(Debug)]
 pub enum CliError {
@@ -57,6 +61,7 @@ macro_rules! pasfmt_config {
         #[derive($crate::command_line::clap::Parser, Debug)]
         #[command(author, about, version, long_about = None)]
         #[clap(max_term_width = 120)]
+        #[command(disable_help_subcommand = true)]
         $(#[$attr])*
         struct $type_name {
             #[command(flatten)]
@@ -135,10 +140,62 @@ pub enum FormatMode {
     Check,
 }
 
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum DumpKind {
+    /// the token stream produced by the lexer
+    Tokens,
+    /// the logical lines produced by the parser
+    Lines,
+    /// the formatting contexts of each logical line
+    Contexts,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// human-readable text
+    Text,
+    /// one JSON object per file
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DumpOptions {
+    pub kind: DumpKind,
+    pub format: DumpFormat,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum PasFmtCommand {
+    /// Write a `pasfmt.toml` documenting every configuration option
+    Init(InitOptions),
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct InitOptions {
+    /// The directory to write `pasfmt.toml` to
+    #[arg(default_value = ".")]
+    pub dir: PathBuf,
+
+    /// Infer option values (e.g. indentation and line endings) by sampling the
+    /// existing source files in the directory
+    #[arg(long)]
+    pub infer: bool,
+
+    /// Overwrite an existing `pasfmt.toml`
+    #[arg(long)]
+    pub force: bool,
+}
+
 fn parse_override(s: &str) -> Result<ConfigOverride, Box<dyn Error + Send + Sync + 'static>> {
     if s.eq_ignore_ascii_case("help") {
         return Ok(ConfigOverride::Help);
     }
+    if s.eq_ignore_ascii_case("show") {
+        return Ok(ConfigOverride::Show);
+    }
+    if s.eq_ignore_ascii_case("schema") {
+        return Ok(ConfigOverride::Schema);
+    }
 
     let pos = s
         .find('=')
@@ -149,9 +206,37 @@ fn parse_override(s: &str) -> Result<ConfigOverride, Box<dyn Error + Send + Sync
     })
 }
 
+/// A configuration option set by a `PASFMT_<KEY>` environment variable.
+#[derive(Debug, Clone)]
+struct EnvOverride {
+    var: String,
+    key: String,
+    val: String,
+}
+
+fn env_overrides() -> Vec<EnvOverride> {
+    env_overrides_from(std::env::vars())
+}
+
+fn env_overrides_from(vars: impl IntoIterator<Item = (String, String)>) -> Vec<EnvOverride> {
+    let prefix = format!("{ENV_PREFIX}_");
+    let mut overrides: Vec<_> = vars
+        .into_iter()
+        .filter(|(var, _)| var != ENV_CONFIG_FILE)
+        .filter_map(|(var, val)| {
+            let key = var.strip_prefix(&prefix)?.to_lowercase().replace("__", ".");
+            Some(EnvOverride { var, key, val })
+        })
+        .collect();
+    overrides.sort_by(|a, b| a.var.cmp(&b.var));
+    overrides
+}
+
 #[derive(Debug, Clone)]
 enum ConfigOverride {
     Help,
+    Show,
+    Schema,
     Set { key: String, val: String },
 }
 
@@ -161,6 +246,9 @@ pub struct PasFmtConfiguration<C: Configuration> {
     #[clap(skip)]
     marker: std::marker::PhantomData<C>,
 
+    #[command(subcommand)]
+    command: Option<PasFmtCommand>,
+
     /// Paths that will be formatted. Can be a path/dir/glob. If no paths are
     /// specified, stdin is read.
     #[arg(index = 1, num_args = 0..)]
@@ -173,13 +261,19 @@ pub struct PasFmtConfiguration<C: Configuration> {
 
     /// Override the configuration file. By default working directory will be
     /// traversed until a `pasfmt.toml` file is found.
-    #[arg(long)]
+    #[arg(long, env = ENV_CONFIG_FILE)]
     config_file: Option<PathBuf>,
 
+    #[clap(skip = env_overrides())]
+    env_overrides: Vec<EnvOverride>,
+
     /// Override one configuration option using KEY=VALUE. This takes
-    /// precedence over `--config-file`.
+    /// precedence over `--config-file` and `PASFMT_<KEY>` environment
+    /// variables.
     ///
-    /// To list available options, use `-C help`.
+    /// To list available options, use `-C help`. To print the resolved
+    /// configuration and where each value came from, use `-C show`. To print a
+    /// JSON Schema of the configuration file, use `-C schema`.
     #[arg(short = 'C', value_parser = parse_override, value_name = "KEY=VALUE")]
     overrides: Vec<ConfigOverride>,
 
@@ -204,6 +298,39 @@ pub struct PasFmtConfiguration<C: Configuration> {
     #[arg(long, value_delimiter = ',', num_args = 1..)]
     cursor: Vec<u32>,
 
+    /// Print (to stdout) the internal representation of the input instead of
+    /// formatting it
+    ///
+    /// This is intended for debugging and bug reports. The output is not
+    /// stable between versions.
+    #[arg(long, value_enum, value_name = "STAGE", conflicts_with_all = ["mode", "cursor"])]
+    dump: Option<DumpKind>,
+
+    /// The output format used by `--dump`
+    #[arg(long, value_enum, default_value_t = DumpFormat::Text, requires = "dump")]
+    dump_format: DumpFormat,
+
+    /// Print (to stdout) an explanation of how the line at the given line
+    /// number was wrapped, instead of formatting
+    ///
+    /// The explanation covers the formatting contexts of the line, the
+    /// decisions that were forced, the penalty of the chosen solution compared
+    /// with the runner-up candidates, and whether the iteration limit was
+    /// reached. Only one file can be explained at a time.
+    ///
+    /// This is intended for debugging and bug reports. The output is not
+    /// stable between versions.
+    #[arg(long, value_name = "LINE", value_parser = clap::value_parser!(u32).range(1..), conflicts_with_all = ["mode", "cursor", "dump"])]
+    explain: Option<u32>,
+
+    /// Write (to FILE) the time spent in each formatting stage for each file,
+    /// as Chrome trace-event JSON
+    ///
+    /// The trace can be viewed with `chrome://tracing` or
+    /// <https://ui.perfetto.dev>.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["dump", "explain"])]
+    profile: Option<PathBuf>,
+
     /// Increase logging verbosity (can be repeated).
     #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "log_level")]
     verbose: u8,
@@ -248,15 +375,115 @@ fn get_styles() -> Styles {
         .placeholder(AnsiColor::White.on_default())
 }
 
+/// The kind of value accepted by a configuration option.
+pub enum ConfigKind {
+    Boolean,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    String,
+    /// One of the listed values.
+    OneOf(&'static [&'static str]),
+    /// One of the listed values, or one of their aliases. Only the values are
+    /// shown in the help.
+    OneOfWithAliases {
+        values: &'static [&'static str],
+        aliases: &'static [&'static str],
+    },
+    /// One of the listed values, or any value described by `name`.
+    OneOfOrNamed {
+        values: &'static [&'static str],
+        name: &'static str,
+    },
+    /// A string, or an array of strings.
+    StringList,
+}
+
+impl ConfigKind {
+    pub const fn unsigned(max: u32) -> Self {
+        ConfigKind::Integer {
+            min: 0,
+            max: max as i64,
+        }
+    }
+
+    /// The JSON Schema of the accepted values.
+    fn schema(&self) -> serde_json::Value {
+        match self {
+            ConfigKind::Boolean => json!({ "type": "boolean" }),
+            ConfigKind::Integer { min, max } => {
+                json!({ "type": "integer", "minimum": min, "maximum": max })
+            }
+            ConfigKind::String => json!({ "type": "string" }),
+            ConfigKind::OneOf(values) => json!({ "type": "string", "enum": values }),
+            ConfigKind::OneOfWithAliases { values, aliases } => json!({
+                "type": "string",
+                "enum": values.iter().chain(aliases.iter()).collect::<Vec<_>>(),
+            }),
+            ConfigKind::OneOfOrNamed { values, .. } => json!({
+                "type": "string",
+                "anyOf": [{ "enum": values }, { "type": "string" }],
+            }),
+            ConfigKind::StringList => json!({
+                "anyOf": [
+                    { "type": "string" },
+                    { "type": "array", "items": { "type": "string" } },
+                ],
+            }),
+        }
+    }
+}
+
+impl Display for ConfigKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigKind::Boolean => f.write_str("<boolean>"),
+            ConfigKind::Integer { min: 0.., .. } => f.write_str("<unsigned integer>"),
+            ConfigKind::Integer { .. } => f.write_str("<integer>"),
+            ConfigKind::String => f.write_str("<string>"),
+            ConfigKind::OneOf(values) | ConfigKind::OneOfWithAliases { values, .. } => {
+                write!(f, "[ {} ]", values.join(" | "))
+            }
+            ConfigKind::OneOfOrNamed { values, name } => {
+                write!(f, "{} | <{name}>", values.join(" | "))
+            }
+            ConfigKind::StringList => f.write_str("<string> | [ <string>, ... ]"),
+        }
+    }
+}
+
 pub struct ConfigItem {
     pub name: &'static str,
     pub description: &'static str,
-    pub hint: &'static str,
+    pub kind: ConfigKind,
     pub default: String,
 }
 
-pub trait Configuration: for<'de> ::serde::Deserialize<'de> {
+pub trait Configuration: for<'de> ::serde::Deserialize<'de> + ::serde::Serialize {
     fn docs() -> impl IntoIterator<Item = ConfigItem>;
+
+    /// Option values inferred from existing source files, used by
+    /// `init --infer`.
+    fn infer(_samples: &[SourceSample]) -> toml::Table {
+        toml::Table::new()
+    }
+}
+
+/// The value of a dotted `key` (e.g. `nested.key`) in `table`.
+pub(crate) fn get_dotted<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let mut segments = key.split('.');
+    let mut value = segments.next().and_then(|s| table.get(s));
+    for segment in segments {
+        value = value.and_then(|v| v.get(segment));
+    }
+    value
+}
+
+/// The default value of each option of `C`.
+pub(crate) fn default_config_values<C: Configuration>() -> anyhow::Result<toml::Table> {
+    let defaults = Config::builder().build()?.try_deserialize::<C>()?;
+    toml::Table::try_from(&defaults).context("failed to serialize configuration")
 }
 
 impl<C: Configuration> PasFmtConfiguration<C> {
@@ -299,7 +526,7 @@ impl<C: Configuration> PasFmtConfiguration<C> {
             writeln!(
                 out,
                 "{cyan}{}{reset} {italic}{}{reset} (default: {yellow}{}{reset})",
-                item.name, item.hint, item.default
+                item.name, item.kind, item.default
             )
             .unwrap();
 
@@ -313,7 +540,32 @@ impl<C: Configuration> PasFmtConfiguration<C> {
         out.into()
     }
 
+    /// The environment variables which set a documented option. Other
+    /// `PASFMT_<KEY>` variables are ignored with a warning, so that they don't
+    /// make every run fail.
+    fn known_env_overrides(&self) -> impl Iterator<Item = &EnvOverride> {
+        let known_keys: Vec<_> = C::docs().into_iter().map(|item| item.name).collect();
+        self.env_overrides.iter().filter(move |o| {
+            let is_known = known_keys.contains(&o.key.as_str());
+            if !is_known {
+                warn!(
+                    "ignoring environment variable `{}`, as `{}` is not a configuration option",
+                    o.var, o.key
+                );
+            }
+            is_known
+        })
+    }
+
     fn get_config_object_from_file(&self, config_file: Option<Cow<Path>>) -> anyhow::Result<C> {
+        self.get_config_resolver_from_file(config_file)?
+            .resolve(&[])
+    }
+
+    fn get_config_resolver_from_file(
+        &self,
+        config_file: Option<Cow<Path>>,
+    ) -> anyhow::Result<ConfigResolver<C>> {
         let mut builder = Config::builder();
 
         if let Some(f) = config_file {
@@ -321,29 +573,276 @@ impl<C: Configuration> PasFmtConfiguration<C> {
             builder = builder.add_source(File::from(f.borrow()).format(FileFormat::Toml));
         }
 
+        // Nested options are set using `__`, e.g. `PASFMT_NESTED__KEY`.
+        builder = builder.add_source(
+            Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator("_")
+                .separator("__")
+                .source(Some(
+                    self.known_env_overrides()
+                        .map(|o| (o.var.clone(), o.val.clone()))
+                        .collect(),
+                )),
+        );
+
+        let mut overrides = vec![];
         for item in &self.overrides {
             match item {
                 ConfigOverride::Set { key, val } => {
                     builder = builder.set_override(key, val.as_ref())?;
+                    overrides.push((key.clone(), val.clone()));
                 }
                 ConfigOverride::Help => {
                     // Do nothing; this is handled in CliWrapperImpl::validate
                 }
+                ConfigOverride::Show | ConfigOverride::Schema => {
+                    // Do nothing; this is handled by the caller via `config_show_requested`
+                }
             }
         }
 
+        Ok(ConfigResolver {
+            base: builder.build()?,
+            overrides,
+            marker: std::marker::PhantomData,
+        })
+    }
+
+    /// The configuration file in use, either from `--config-file` or found in
+    /// the current directory or one of its parents.
+    pub fn get_config_file(&self) -> anyhow::Result<Option<Cow<'_, Path>>> {
+        Ok(match &self.config_file {
+            Some(file) => Some(Cow::Borrowed(file.as_path())),
+            None => Self::find_config_file(std::env::current_dir()?).map(Cow::Owned),
+        })
+    }
+
+    pub fn get_config_object(&self) -> anyhow::Result<C> {
+        self.get_config_object_from_file(self.get_config_file()?)
+    }
+
+    /// The resolver of the configuration for files with their own settings.
+    pub fn get_config_resolver(&self) -> anyhow::Result<ConfigResolver<C>> {
+        self.get_config_resolver_from_file(self.get_config_file()?)
+    }
+
+    pub fn command(&self) -> Option<&PasFmtCommand> {
+        self.command.as_ref()
+    }
+
+    /// Whether `-C show` was used to request the resolved configuration
+    /// instead of formatting.
+    pub fn config_show_requested(&self) -> bool {
+        self.overrides
+            .iter()
+            .any(|o| matches!(o, ConfigOverride::Show))
+    }
+
+    /// Whether `-C schema` was used to request the JSON Schema of the
+    /// configuration file instead of formatting.
+    pub fn config_schema_requested(&self) -> bool {
+        self.overrides
+            .iter()
+            .any(|o| matches!(o, ConfigOverride::Schema))
+    }
+
+    /// The JSON Schema of the configuration file, built from
+    /// [`Configuration::docs`]. Options with dotted names are nested in
+    /// tables.
+    pub fn config_schema() -> anyhow::Result<String> {
+        fn object() -> serde_json::Value {
+            json!({ "type": "object", "properties": {}, "additionalProperties": false })
+        }
+
+        let defaults = default_config_values::<C>()?;
+        let mut root = object();
+        for item in C::docs() {
+            let mut schema = item.kind.schema();
+            schema["description"] = item.description.trim_end().into();
+            if let Some(default) = get_dotted(&defaults, item.name) {
+                schema["default"] = serde_json::to_value(default)?;
+            }
+
+            let mut segments: Vec<_> = item.name.split('.').collect();
+            let name = segments.pop().unwrap_or(item.name);
+            let mut table = &mut root;
+            for segment in segments {
+                table = table["properties"]
+                    .as_object_mut()
+                    .context("schema properties must be an object")?
+                    .entry(segment)
+                    .or_insert_with(object);
+            }
+            table["properties"][name] = schema;
+        }
+
+        root["$schema"] = "http://json-schema.org/draft-07/schema#".into();
+        root["title"] = DEFAULT_CONFIG_FILE_NAME.into();
+        Ok(serde_json::to_string_pretty(&root)? + "\n")
+    }
+
+    /// The resolved configuration as TOML, with each value annotated with its
+    /// source.
+    pub fn show_config(&self) -> anyhow::Result<String> {
+        self.show_config_from_file(self.get_config_file()?.as_deref())
+    }
+
+    /// Whether the value of `key` comes from the configuration file, rather
+    /// than from the defaults, an environment variable or a `-C` override.
+    pub fn is_set_by_config_file(&self, key: &str) -> anyhow::Result<bool> {
+        let Some(config_file) = self.get_config_file()? else {
+            return Ok(false);
+        };
+        let file_table = read_to_string(&config_file)?
+            .parse::<toml::Table>()
+            .with_context(|| format!("failed to parse {}", config_file.display()))?;
+
+        let sources = ConfigSources {
+            file: Some((&config_file, &file_table)),
+            env_overrides: &self.env_overrides,
+            overrides: &self.overrides,
+        };
+        Ok(matches!(sources.source(key), ConfigSource::File(_)))
+    }
+
+    fn show_config_from_file(&self, config_file: Option<&Path>) -> anyhow::Result<String> {
+        use std::fmt::Write;
+
+        let config = self.get_config_object_from_file(config_file.map(Cow::Borrowed))?;
+        let toml::Value::Table(resolved) =
+            toml::Value::try_from(&config).context("failed to serialize configuration")?
+        else {
+            anyhow::bail!("configuration did not serialize to a table");
+        };
+
+        let file_table = match config_file {
+            Some(path) => read_to_string(path)?
+                .parse::<toml::Table>()
+                .with_context(|| format!("failed to parse {}", path.display()))?,
+            None => toml::Table::new(),
+        };
+
+        let mut out = String::new();
+        match config_file {
+            Some(path) => writeln!(out, "# config file: {}", path.display())?,
+            None => writeln!(out, "# config file: none")?,
+        }
+        let sources = ConfigSources {
+            file: config_file.map(|path| (path, &file_table)),
+            env_overrides: &self.env_overrides,
+            overrides: &self.overrides,
+        };
+        sources.write_table(&mut out, "", &resolved)?;
+
+        Ok(out)
+    }
+}
+
+/// Resolves the configuration of a file, given the settings from a
+/// `// pasfmt: key=value` comment in its header.
+///
+/// These settings take precedence over the configuration file and
+/// environment variables, but not over `-C` overrides.
+pub struct ConfigResolver<C> {
+    base: Config,
+    overrides: Vec<(String, String)>,
+    marker: std::marker::PhantomData<C>,
+}
+
+impl<C: Configuration> ConfigResolver<C> {
+    pub fn resolve(&self, inline_settings: &[InlineSetting]) -> anyhow::Result<C> {
+        let mut builder = Config::builder().add_source(self.base.clone());
+        for setting in inline_settings {
+            builder = builder.set_override(&setting.key, setting.value.as_str())?;
+        }
+        for (key, val) in &self.overrides {
+            builder = builder.set_override(key, val.as_str())?;
+        }
+
         builder
             .build()?
             .try_deserialize::<C>()
             .context("failed to construct configuration")
     }
+}
 
-    pub fn get_config_object(&self) -> anyhow::Result<C> {
-        let config_file = match &self.config_file {
-            Some(file) => Some(Cow::Borrowed(file.as_path())),
-            None => Self::find_config_file(std::env::current_dir()?).map(Cow::Owned),
+enum ConfigSource<'a> {
+    Default,
+    File(&'a Path),
+    Env { var: &'a str, val: &'a str },
+    Override { key: &'a str, val: &'a str },
+}
+
+impl Display for ConfigSource<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigSource::Default => f.write_str("default"),
+            ConfigSource::File(path) => write!(f, "from {}", path.display()),
+            ConfigSource::Env { var, val } => write!(f, "from {var}={val}"),
+            ConfigSource::Override { key, val } => write!(f, "from -C {key}={val}"),
+        }
+    }
+}
+
+struct ConfigSources<'a> {
+    file: Option<(&'a Path, &'a toml::Table)>,
+    env_overrides: &'a [EnvOverride],
+    overrides: &'a [ConfigOverride],
+}
+
+impl ConfigSources<'_> {
+    fn source(&self, key: &str) -> ConfigSource<'_> {
+        let overridden = self.overrides.iter().rev().find_map(|o| match o {
+            ConfigOverride::Set { key: k, val }
+                if k == key
+                    || key
+                        .strip_prefix(k.as_str())
+                        .is_some_and(|r| r.starts_with('.')) =>
+            {
+                Some(ConfigSource::Override { key: k, val })
+            }
+            _ => None,
+        });
+        if let Some(source) = overridden {
+            return source;
+        }
+
+        if let Some(o) = self.env_overrides.iter().find(|o| o.key == key) {
+            return ConfigSource::Env {
+                var: &o.var,
+                val: &o.val,
+            };
+        }
+
+        if let Some((path, table)) = self.file
+            && get_dotted(table, key).is_some()
+        {
+            return ConfigSource::File(path);
+        }
+
+        ConfigSource::Default
+    }
+
+    fn write_table(&self, out: &mut String, prefix: &str, table: &toml::Table) -> std::fmt::Result {
+        use std::fmt::Write;
+
+        let key_of = |name: &str| match prefix {
+            "" => name.to_owned(),
+            _ => format!("{prefix}.{name}"),
         };
-        self.get_config_object_from_file(config_file)
+
+        for (name, value) in table.iter().filter(|(_, v)| !v.is_table()) {
+            writeln!(out, "{name} = {value} # {}", self.source(&key_of(name)))?;
+        }
+        for (name, value) in table {
+            if let toml::Value::Table(inner) = value {
+                let key = key_of(name);
+                writeln!(out, "\n[{key}]")?;
+                self.write_table(out, &key, inner)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -384,24 +883,39 @@ impl<C: Configuration> FormatterConfiguration for PasFmtConfiguration<C> {
     fn cursors(&self) -> &[u32] {
         &self.cursor
     }
+
+    fn dump(&self) -> Option<DumpOptions> {
+        self.dump.map(|kind| DumpOptions {
+            kind,
+            format: self.dump_format,
+        })
+    }
+
+    fn explain(&self) -> Option<u32> {
+        self.explain
+    }
+
+    fn profile(&self) -> Option<&Path> {
+        self.profile.as_deref()
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use assert_fs::{TempDir, prelude::*};
-    use serde::Deserialize;
+    use serde::{Deserialize, Serialize};
     use spectral::prelude::*;
 
     pasfmt_config!(Config<Settings>);
 
-    #[derive(Deserialize, PartialEq, Eq, Debug)]
+    #[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
     enum SettingEnum {
         A,
         B,
     }
 
-    #[derive(Deserialize, PartialEq, Eq, Default, Debug)]
+    #[derive(Serialize, Deserialize, PartialEq, Eq, Default, Debug)]
     #[serde(deny_unknown_fields)]
     struct Nested {
         #[serde(default)]
@@ -410,7 +924,7 @@ mod tests {
         baz: Option<SettingEnum>,
     }
 
-    #[derive(Deserialize, Debug, PartialEq, Eq, Default)]
+    #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Default)]
     #[serde(deny_unknown_fields)]
     struct Settings {
         #[serde(default)]
@@ -425,7 +939,12 @@ mod tests {
 
     impl Configuration for Settings {
         fn docs() -> impl IntoIterator<Item = ConfigItem> {
-            []
+            ["foo", "bar", "baz", "nested.bar", "nested.baz"].map(|name| ConfigItem {
+                name,
+                description: "",
+                kind: ConfigKind::String,
+                default: String::new(),
+            })
         }
     }
 
@@ -711,6 +1230,120 @@ mod tests {
             Ok(())
         }
 
+        #[test]
+        fn show_config_names_value_sources() -> Result<(), Box<dyn Error>> {
+            let tmp = TempDir::new()?;
+            let config_file = &tmp.child("custom_settings.toml");
+            config_file.write_str(indoc! {r#"
+                foo = "foo!"
+                [nested]
+                bar = 1
+            "#})?;
+
+            let config = config(&["", "-C", "show", "-Cbar=-1", "-Cnested.baz=B"])?;
+            assert!(config.config_show_requested());
+
+            let shown = config.show_config_from_file(Some(config_file))?;
+            assert_eq!(
+                shown,
+                format!(
+                    indoc! {r#"
+                        # config file: {path}
+                        foo = "foo!" # from {path}
+                        bar = -1 # from -C bar=-1
+
+                        [nested]
+                        bar = 1 # from {path}
+                        baz = "B" # from -C nested.baz=B
+                    "#},
+                    path = config_file.display()
+                )
+            );
+
+            let shown = config.show_config_from_file(None)?;
+            assert_that(&shown).starts_with("# config file: none\nfoo = \"\" # default\n");
+
+            Ok(())
+        }
+
+        #[test]
+        fn env_overrides_from_vars() {
+            let overrides = env_overrides_from([
+                ("PASFMT_FOO".to_owned(), "a".to_owned()),
+                ("PASFMT_NESTED__BAR".to_owned(), "1".to_owned()),
+                ("PASFMT_CONFIG".to_owned(), "pasfmt.toml".to_owned()),
+                ("PASFMTFOO".to_owned(), "b".to_owned()),
+                ("OTHER".to_owned(), "c".to_owned()),
+            ]);
+            let overrides: Vec<_> = overrides
+                .iter()
+                .map(|o| (o.var.as_str(), o.key.as_str(), o.val.as_str()))
+                .collect();
+            assert_eq!(
+                overrides,
+                [
+                    ("PASFMT_FOO", "foo", "a"),
+                    ("PASFMT_NESTED__BAR", "nested.bar", "1"),
+                ]
+            );
+        }
+
+        #[test]
+        fn unknown_env_overrides_are_ignored() -> Result<(), Box<dyn Error>> {
+            let mut config = config(&[""])?;
+            config.env_overrides = env_overrides_from([
+                ("PASFMT_FOO".to_owned(), "env".to_owned()),
+                ("PASFMT_QUX".to_owned(), "1".to_owned()),
+                ("PASFMT_NESTED__QUX".to_owned(), "2".to_owned()),
+            ]);
+
+            let obj: Settings = config.get_config_object_from_file(None)?;
+            assert_eq!(
+                obj,
+                Settings {
+                    foo: "env".to_owned(),
+                    ..Default::default()
+                }
+            );
+
+            Ok(())
+        }
+
+        #[test]
+        fn env_overrides_are_between_file_and_overrides() -> Result<(), Box<dyn Error>> {
+            let tmp = TempDir::new()?;
+            let config_file = &tmp.child("custom_settings.toml");
+            config_file.write_str("foo = \"file\"\nbar = 1")?;
+
+            let mut config = config(&["", "-Cbar=3"])?;
+            config.env_overrides = env_overrides_from([
+                ("PASFMT_FOO".to_owned(), "env".to_owned()),
+                ("PASFMT_BAR".to_owned(), "2".to_owned()),
+                ("PASFMT_NESTED__BAZ".to_owned(), "A".to_owned()),
+            ]);
+
+            let obj: Settings =
+                config.get_config_object_from_file(Some(Cow::Borrowed(config_file)))?;
+            assert_eq!(
+                obj,
+                Settings {
+                    foo: "env".to_owned(),
+                    bar: 3,
+                    baz: None,
+                    nested: Nested {
+                        bar: 0,
+                        baz: Some(SettingEnum::A)
+                    },
+                }
+            );
+
+            let shown = config.show_config_from_file(Some(config_file))?;
+            assert_that(&shown).contains("foo = \"env\" # from PASFMT_FOO=env\n");
+            assert_that(&shown).contains("bar = 3 # from -C bar=3\n");
+
+            Ok(())
+        }
+
         #[test]
         fn config_overrides_have_greater_precedence_than_file() -> Result<(), Box<dyn Error>> {
             let tmp = TempDir::new()?;
@@ -0,0 +1,283 @@
+use std::fmt::Write;
+
+use pasfmt_core::prelude::*;
+use serde_json::{Value, json};
+
+use crate::command_line::{DumpFormat, DumpKind, DumpOptions};
+
+/// Renders the internal representation of `input` at the requested stage of
+/// the formatting pipeline.
+///
+/// The text format is intended to be read by humans, and the JSON format is a
+/// single object per input so that output for multiple files can be emitted as
+/// JSON lines.
+pub(crate) fn dump(
+    formatter: &Formatter,
+    input: &str,
+    path: Option<&str>,
+    options: DumpOptions,
+) -> String {
+    match (options.kind, options.format) {
+        (DumpKind::Tokens, DumpFormat::Text) => tokens_text(&formatter.lex(input)),
+        (DumpKind::Tokens, DumpFormat::Json) => {
+            json_object(path, "tokens", tokens_json(&formatter.lex(input)))
+        }
+        (DumpKind::Lines, format) => {
+            let (lines, tokens) = formatter.parse(input);
+            match format {
+                DumpFormat::Text => lines_text(&tokens, &lines),
+                DumpFormat::Json => json_object(path, "lines", lines_json(&lines)),
+            }
+        }
+        (DumpKind::Contexts, format) => {
+            let (lines, tokens) = formatter.parse(input);
+            let contexts = dump_contexts(&tokens, &lines);
+            match format {
+                DumpFormat::Text => contexts_text(&contexts),
+                DumpFormat::Json => json_object(path, "contexts", contexts_json(&contexts)),
+            }
+        }
+    }
+}
+
+fn json_object(path: Option<&str>, key: &str, value: Value) -> String {
+    let mut object = serde_json::Map::new();
+    if let Some(path) = path {
+        object.insert("path".into(), path.into());
+    }
+    object.insert(key.into(), value);
+    Value::Object(object).to_string()
+}
+
+fn tokens_text(tokens: &[RawToken]) -> String {
+    let mut out = String::new();
+    for (index, token) in tokens.iter().enumerate() {
+        writeln!(
+            out,
+            "{index}: {:?} {:?} (whitespace: {:?})",
+            token.get_token_type(),
+            token.get_content(),
+            token.get_leading_whitespace(),
+        )
+        .unwrap();
+    }
+    out
+}
+
+fn tokens_json(tokens: &[RawToken]) -> Value {
+    tokens
+        .iter()
+        .enumerate()
+        .map(|(index, token)| {
+            json!({
+                "index": index,
+                "type": format!("{:?}", token.get_token_type()),
+                "content": token.get_content(),
+                "leading_whitespace": token.get_leading_whitespace(),
+            })
+        })
+        .collect()
+}
+
+fn line_depth(lines: &[LogicalLine], line: &LogicalLine) -> usize {
+    std::iter::successors(line.get_parent(), |parent| {
+        lines[parent.line_index].get_parent()
+    })
+    .count()
+}
+
+fn lines_text(tokens: &[Token], lines: &[LogicalLine]) -> String {
+    let mut out = String::new();
+    for (index, line) in lines.iter().enumerate() {
+        let depth = line_depth(lines, line);
+        write!(
+            out,
+            "{:indent$}{index}: {:?} (level: {}",
+            "",
+            line.get_line_type(),
+            line.get_level(),
+            indent = depth * 2,
+        )
+        .unwrap();
+        if let Some(parent) = line.get_parent() {
+            write!(
+                out,
+                ", parent: line {} token {}",
+                parent.line_index, parent.global_token_index
+            )
+            .unwrap();
+        }
+        let content = line
+            .get_tokens()
+            .iter()
+            .map(|&token| tokens[token].get_content())
+            .collect::<Vec<_>>()
+            .join(" ");
+        write!(out, ") {:?}", line.get_tokens()).unwrap();
+        match content.is_empty() {
+            true => writeln!(out).unwrap(),
+            false => writeln!(out, " {content}").unwrap(),
+        }
+    }
+    out
+}
+
+fn lines_json(lines: &[LogicalLine]) -> Value {
+    lines
+        .iter()
+        .enumerate()
+        .map(|(index, line)| {
+            json!({
+                "index": index,
+                "type": format!("{:?}", line.get_line_type()),
+                "level": line.get_level(),
+                "parent": line.get_parent().map(|parent| json!({
+                    "line_index": parent.line_index,
+                    "token_index": parent.global_token_index,
+                })),
+                "tokens": line.get_tokens(),
+            })
+        })
+        .collect()
+}
+
+fn contexts_text(lines: &[LineContextsDump]) -> String {
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+fn contexts_json(lines: &[LineContextsDump]) -> Value {
+    lines
+        .iter()
+        .map(|line| {
+            json!({
+                "line_index": line.line_index,
+                "line_type": format!("{:?}", line.line_type),
+                "contexts": line.contexts.iter().map(|ctx| json!({
+                    "id": ctx.id,
+                    "parent": ctx.parent,
+                    "type": ctx.context_type,
+                    "continuation_delta": ctx.continuation_delta,
+                    "starting_token": ctx.starting_token,
+                    "ending_token": ctx.ending_token,
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatter() -> Formatter {
+        Formatter::builder()
+            .lexer(DelphiLexer {})
+            .parser(DelphiLogicalLineParser {})
+            .reconstructor(DelphiLogicalLinesReconstructor::new(
+                ReconstructionSettings::new(LineEnding::Lf, TabKind::Soft, 2, 4),
+            ))
+            .build()
+    }
+
+    fn options(kind: DumpKind, format: DumpFormat) -> DumpOptions {
+        DumpOptions { kind, format }
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            dump(
+                &formatter(),
+                "A := 1;",
+                None,
+                options(DumpKind::Tokens, DumpFormat::Text)
+            ),
+            "\
+0: Identifier \"A\" (whitespace: \"\")
+1: Op(Assign) \":=\" (whitespace: \" \")
+2: NumberLiteral(Decimal) \"1\" (whitespace: \" \")
+3: Op(Semicolon) \";\" (whitespace: \"\")
+4: Eof \"\" (whitespace: \"\")
+"
+        );
+    }
+
+    #[test]
+    fn tokens_json() {
+        let output = dump(
+            &formatter(),
+            "A;",
+            Some("a.pas"),
+            options(DumpKind::Tokens, DumpFormat::Json),
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&output).unwrap(),
+            json!({
+                "path": "a.pas",
+                "tokens": [
+                    {"index": 0, "type": "Identifier", "content": "A", "leading_whitespace": ""},
+                    {"index": 1, "type": "Op(Semicolon)", "content": ";", "leading_whitespace": ""},
+                    {"index": 2, "type": "Eof", "content": "", "leading_whitespace": ""},
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            dump(
+                &formatter(),
+                "if A then B;",
+                None,
+                options(DumpKind::Lines, DumpFormat::Text)
+            ),
+            "\
+0: Unknown (level: 0) [0, 1, 2] if A then
+  1: Unknown (level: 1, parent: line 0 token 2) [3, 4] B ;
+2: Eof (level: 0) [5]
+"
+        );
+    }
+
+    #[test]
+    fn lines_json() {
+        let output = dump(
+            &formatter(),
+            "if A then B;",
+            None,
+            options(DumpKind::Lines, DumpFormat::Json),
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&output).unwrap()["lines"][1],
+            json!({
+                "index": 1,
+                "type": "Unknown",
+                "level": 1,
+                "parent": {"line_index": 0, "token_index": 2},
+                "tokens": [3, 4],
+            })
+        );
+    }
+
+    #[test]
+    fn contexts() {
+        assert_eq!(
+            dump(
+                &formatter(),
+                "A := Foo(B, C + D);",
+                None,
+                options(DumpKind::Contexts, DumpFormat::Text)
+            ),
+            "\
+line 0 (Assignment):
+  Base [0..] (continuation delta: 1)
+    AssignRHS [2..9] (continuation delta: 1)
+      Brackets(Round, BreakClose) [3..9] (continuation delta: 1)
+        CommaList [4..8] (continuation delta: 0)
+          CommaElem [6..8] (continuation delta: 1)
+            Precedence(3) [6..8] (continuation delta: 1)
+"
+        );
+    }
+}
@@ -1,24 +1,31 @@
-use anyhow::{Context, bail};
+use anyhow::{Context, anyhow, bail};
 use encoding_rs::Encoding;
 use log::*;
 use std::{
     borrow::Cow,
+    collections::HashMap,
     fmt::Display,
     fs::{File, OpenOptions},
     io::{self, IsTerminal, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
+    sync::{Arc, Mutex},
     time::Instant,
 };
 
 use glob::glob;
 use pasfmt_core::{
     formatter::Formatter,
-    prelude::{Cursor, FileOptions},
+    prelude::{Cursor, Diagnostic, FileOptions, InlineSetting, find_inline_settings},
 };
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
-use crate::ErrHandler;
+use crate::{
+    ErrHandler,
+    command_line::{DumpFormat, DumpOptions},
+    dump::dump,
+    profile::Profiler,
+};
 
 type WriteResult = std::io::Result<u64>;
 
@@ -28,15 +35,47 @@ struct DecodedFile<'a> {
     encoding: &'static Encoding,
 }
 
+type MakeInlineFormatter = Box<dyn Fn(&[InlineSetting]) -> anyhow::Result<Formatter> + Sync>;
+
 pub struct FileFormatter {
     formatter: Formatter,
     encoding: &'static encoding_rs::Encoding,
+    profiler: Option<Profiler>,
+    make_inline_formatter: Option<MakeInlineFormatter>,
+    /// The formatters made for each distinct set of inline settings, as making
+    /// a formatter can require reading files.
+    inline_formatters: Mutex<HashMap<Vec<InlineSetting>, Arc<Formatter>>>,
 }
 impl FileFormatter {
     pub fn new(formatter: Formatter, encoding: &'static encoding_rs::Encoding) -> Self {
         FileFormatter {
             formatter,
             encoding,
+            profiler: None,
+            make_inline_formatter: None,
+            inline_formatters: Mutex::default(),
+        }
+    }
+
+    /// Formats files with `// pasfmt: key=value` settings in their header
+    /// using the formatter made for those settings.
+    pub fn with_inline_settings(
+        mut self,
+        make_formatter: impl Fn(&[InlineSetting]) -> anyhow::Result<Formatter> + Sync + 'static,
+    ) -> Self {
+        self.make_inline_formatter = Some(Box::new(make_formatter));
+        self
+    }
+
+    pub(crate) fn with_profiler(mut self) -> Self {
+        self.profiler = Some(Profiler::new());
+        self
+    }
+
+    pub(crate) fn write_profile(&self, path: &Path) -> anyhow::Result<()> {
+        match &self.profiler {
+            Some(profiler) => profiler.write(path),
+            None => Ok(()),
         }
     }
 
@@ -115,29 +154,88 @@ impl FileFormatter {
         eprintln!("CURSOR={}", cursors.join(","));
     }
 
-    fn exec_format<S, T, E>(
+    /// Reports each diagnostic as a warning of the form `<PATH>:<LINE>: <MESSAGE>`.
+    fn report_diagnostics(diagnostics: &[Diagnostic], path: impl Display) {
+        for diagnostic in diagnostics {
+            warn!("{path}:{}: {diagnostic}", diagnostic.line_number);
+        }
+    }
+
+    /// The formatter for an input with `// pasfmt: key=value` settings in its
+    /// header, if it has any.
+    fn inline_formatter(
         &self,
-        paths: &[S],
+        input: &str,
+        path: impl Display,
+    ) -> anyhow::Result<Option<Arc<Formatter>>> {
+        let Some(make_formatter) = &self.make_inline_formatter else {
+            return Ok(None);
+        };
+
+        let settings = find_inline_settings(input);
+        if settings.is_empty() {
+            return Ok(None);
+        }
+        if let Some(formatter) = self.inline_formatters.lock().unwrap().get(&settings) {
+            return Ok(Some(formatter.clone()));
+        }
+
+        let formatter = Arc::new(
+            make_formatter(&settings)
+                .with_context(|| format!("invalid `pasfmt:` settings in '{path}'"))?,
+        );
+        let mut inline_formatters = self.inline_formatters.lock().unwrap();
+        Ok(Some(
+            inline_formatters
+                .entry(settings)
+                .or_insert(formatter)
+                .clone(),
+        ))
+    }
+
+    /// Formats the input, reporting any diagnostics and recording the time
+    /// spent when profiling.
+    fn format_input(
+        &self,
+        input: &str,
+        path: impl Display,
+        cursors: &mut [Cursor],
+    ) -> anyhow::Result<String> {
+        let path = path.to_string();
+        let inline_formatter = self.inline_formatter(input, &path)?;
+        let formatter = inline_formatter.as_deref().unwrap_or(&self.formatter);
+
+        let mut diagnostics = vec![];
+        let mut stage_timings = vec![];
+        let mut options = FileOptions::new()
+            .with_cursors(cursors)
+            .with_diagnostics(&mut diagnostics);
+        if self.profiler.is_some() {
+            options = options.with_stage_timings(&mut stage_timings);
+        }
+
+        let start = Instant::now();
+        let output = formatter.format(input, options);
+        if let Some(profiler) = &self.profiler {
+            profiler.record(&path, start, &stage_timings);
+        }
+
+        Self::report_diagnostics(&diagnostics, path);
+        Ok(output)
+    }
+
+    fn exec_on_files<T, E>(
+        &self,
+        paths: Vec<Result<PathBuf, anyhow::Error>>,
         mut open_options: OpenOptions,
-        result_operation: T,
+        operation: T,
         error_handler: E,
-        cursors: &[u32],
     ) where
-        S: AsRef<str>,
         E: ErrHandler,
-        T: Fn(&mut File, &Path, &DecodedFile, &str) -> anyhow::Result<()> + Sync,
+        T: Fn(&mut File, &Path, &DecodedFile) -> anyhow::Result<()> + Sync,
     {
         open_options.read(true);
 
-        let paths = self.expand_paths(paths);
-
-        let cursors = if paths.len() > 1 && !cursors.is_empty() {
-            warn!("cursor position cannot be tracked when formatting more than one file");
-            &[]
-        } else {
-            cursors
-        };
-
         paths
             .into_par_iter()
             .map_init(Vec::<u8>::new, |input_buf, file_path| {
@@ -152,27 +250,59 @@ impl FileFormatter {
                     .decode_file(&mut file, file_path.display(), input_buf)
                     .with_context(|| format!("failed to read '{}'", file_path.display()))?;
 
+                operation(&mut file, &file_path, &decoded_file)
+            })
+            .for_each(|res| {
+                if let Err(e) = res {
+                    error_handler(e);
+                };
+            });
+    }
+
+    fn exec_format<S, T, E>(
+        &self,
+        paths: &[S],
+        open_options: OpenOptions,
+        result_operation: T,
+        error_handler: E,
+        cursors: &[u32],
+    ) where
+        S: AsRef<str>,
+        E: ErrHandler,
+        T: Fn(&mut File, &Path, &DecodedFile, &str) -> anyhow::Result<()> + Sync,
+    {
+        let paths = self.expand_paths(paths);
+
+        let cursors = if paths.len() > 1 && !cursors.is_empty() {
+            warn!("cursor position cannot be tracked when formatting more than one file");
+            &[]
+        } else {
+            cursors
+        };
+
+        self.exec_on_files(
+            paths,
+            open_options,
+            |file, file_path, decoded_file| {
                 let mut inner_cursors: Vec<_> = cursors.iter().map(|c| Cursor(*c)).collect();
 
                 debug!("Formatting {}", file_path.display());
                 let time = Instant::now();
-                let output = self.formatter.format(
+                let output = self.format_input(
                     &decoded_file.contents,
-                    FileOptions::new().with_cursors(&mut inner_cursors),
-                );
+                    file_path.display(),
+                    &mut inner_cursors,
+                )?;
                 debug!("Formatted {} in {:?}", file_path.display(), time.elapsed());
 
                 if !inner_cursors.is_empty() {
                     Self::output_new_cursors(&inner_cursors);
                 }
 
-                result_operation(&mut file, &file_path, &decoded_file, &output)
-            })
-            .for_each(|res| {
-                if let Err(e) = res {
-                    error_handler(e);
-                };
-            });
+                result_operation(file, file_path, decoded_file, &output)
+            },
+            error_handler,
+        );
     }
 
     pub(crate) fn format_files<S: AsRef<str>>(
@@ -307,10 +437,8 @@ impl FileFormatter {
 
             let mut cursors: Vec<_> = cursors.iter().map(|c| Cursor(*c)).collect();
 
-            let formatted_input = self.formatter.format(
-                &decoded_stdin.contents,
-                FileOptions::new().with_cursors(&mut cursors),
-            );
+            let formatted_input =
+                self.format_input(&decoded_stdin.contents, "<stdin>", &mut cursors)?;
             Self::write_stdout(&decoded_stdin, &formatted_input)
                 .context("failed to write to stdout")?;
 
@@ -380,9 +508,7 @@ impl FileFormatter {
         let inner = || {
             let mut buf = vec![];
             let decoded_stdin = self.decode_stdin(&mut buf)?;
-            let formatted_input = self
-                .formatter
-                .format(&decoded_stdin.contents, FileOptions::new());
+            let formatted_input = self.format_input(&decoded_stdin.contents, "<stdin>", &mut [])?;
             Self::check_formatting(&decoded_stdin.contents, &formatted_input, "<stdin>")
         };
 
@@ -390,9 +516,108 @@ impl FileFormatter {
             error_handler(e);
         }
     }
+
+    pub(crate) fn dump_files<S: AsRef<str>>(
+        &self,
+        paths: &[S],
+        options: DumpOptions,
+        error_handler: impl ErrHandler,
+    ) {
+        let paths = self.expand_paths(paths);
+        let order: Vec<PathBuf> = paths.iter().flatten().cloned().collect();
+
+        // The files are dumped in parallel, but printed in the order they were
+        // given so that the output is deterministic.
+        let outputs = Mutex::new(HashMap::new());
+        self.exec_on_files(
+            paths,
+            OpenOptions::new(),
+            |_, file_path, decoded_file| {
+                let path = file_path.display().to_string();
+                let output = dump(
+                    &self.formatter,
+                    &decoded_file.contents,
+                    Some(&path),
+                    options,
+                );
+                outputs
+                    .lock()
+                    .unwrap()
+                    .insert(file_path.to_path_buf(), output);
+                Ok(())
+            },
+            error_handler,
+        );
+
+        let outputs = outputs.into_inner().unwrap();
+        for file_path in order {
+            let Some(output) = outputs.get(&file_path) else {
+                continue;
+            };
+            match options.format {
+                // Mirrors the human-readable concatenation of `stdout` mode.
+                DumpFormat::Text => print!("{}:\n{output}\n", file_path.display()),
+                DumpFormat::Json => println!("{output}"),
+            }
+        }
+    }
+
+    /// Formats the input only for the explanation made by the formatter.
+    fn explain_input(&self, input: &str, path: impl Display) -> anyhow::Result<()> {
+        let inline_formatter = self.inline_formatter(input, path)?;
+        let formatter = inline_formatter.as_deref().unwrap_or(&self.formatter);
+        formatter.format(input, FileOptions::new());
+        Ok(())
+    }
+
+    pub(crate) fn explain_file<S: AsRef<str>>(&self, paths: &[S], error_handler: impl ErrHandler) {
+        let paths = self.expand_paths(paths);
+        if paths.len() != 1 {
+            error_handler(anyhow!(
+                "exactly one file can be explained, but {} were provided",
+                paths.len()
+            ));
+            return;
+        }
+        self.exec_on_files(
+            paths,
+            OpenOptions::new(),
+            |_, file_path, decoded_file| {
+                self.explain_input(&decoded_file.contents, file_path.display())
+            },
+            error_handler,
+        );
+    }
+    pub(crate) fn explain_stdin(&self, error_handler: impl ErrHandler) {
+        let inner = || {
+            let mut buf = vec![];
+            let decoded_stdin = self.decode_stdin(&mut buf)?;
+            self.explain_input(&decoded_stdin.contents, "<stdin>")
+        };
+
+        if let Err(e) = inner() {
+            error_handler(e);
+        }
+    }
+    pub(crate) fn dump_stdin(&self, options: DumpOptions, error_handler: impl ErrHandler) {
+        let inner = || {
+            let mut buf = vec![];
+            let decoded_stdin = self.decode_stdin(&mut buf)?;
+            let output = dump(&self.formatter, &decoded_stdin.contents, None, options);
+            match options.format {
+                DumpFormat::Text => print!("{output}"),
+                DumpFormat::Json => println!("{output}"),
+            }
+            Ok(())
+        };
+
+        if let Err(e) = inner() {
+            error_handler(e);
+        }
+    }
 }
 
-fn formattable_file_path(path: &Path) -> bool {
+pub(crate) fn formattable_file_path(path: &Path) -> bool {
     match path.extension() {
         Some(ext) => {
             ext.eq_ignore_ascii_case("pas")
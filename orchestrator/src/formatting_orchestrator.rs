@@ -1,6 +1,10 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, path::Path};
 
-use crate::{command_line::FormatMode, file_formatter::FileFormatter, predule::ErrHandler};
+use crate::{
+    command_line::{DumpOptions, FormatMode},
+    file_formatter::FileFormatter,
+    predule::ErrHandler,
+};
 use log::LevelFilter;
 
 pub trait FormatterConfiguration {
@@ -9,6 +13,12 @@ pub trait FormatterConfiguration {
     fn log_level(&self) -> LevelFilter;
     fn mode(&self) -> FormatMode;
     fn cursors(&self) -> &[u32];
+    fn dump(&self) -> Option<DumpOptions>;
+    /// The line number whose formatting should be explained. The formatter is
+    /// expected to report the explanation itself; the output is discarded.
+    fn explain(&self) -> Option<u32>;
+    /// The file to write the per-stage formatting timings to.
+    fn profile(&self) -> Option<&Path>;
 }
 
 pub struct FormattingOrchestrator;
@@ -18,6 +28,36 @@ impl FormattingOrchestrator {
         config: impl FormatterConfiguration,
         err_handler: impl ErrHandler,
     ) {
+        if let Some(dump) = config.dump() {
+            if config.is_stdin() {
+                file_formatter.dump_stdin(dump, err_handler);
+            } else {
+                match config.get_paths() {
+                    Ok(paths) => file_formatter.dump_files(&paths, dump, err_handler),
+                    Err(e) => err_handler(e),
+                }
+            }
+            return;
+        }
+
+        if config.explain().is_some() {
+            if config.is_stdin() {
+                file_formatter.explain_stdin(err_handler);
+            } else {
+                match config.get_paths() {
+                    Ok(paths) => file_formatter.explain_file(&paths, err_handler),
+                    Err(e) => err_handler(e),
+                }
+            }
+            return;
+        }
+
+        let file_formatter = match config.profile() {
+            Some(_) => file_formatter.with_profiler(),
+            None => file_formatter,
+        };
+        let err_handler = &err_handler;
+
         match config.mode() {
             FormatMode::Check if config.is_stdin() => file_formatter.check_stdin(err_handler),
             FormatMode::Stdout if config.is_stdin() => {
@@ -36,5 +76,11 @@ impl FormattingOrchestrator {
                 Err(e) => err_handler(e),
             },
         };
+
+        if let Some(path) = config.profile()
+            && let Err(e) = file_formatter.write_profile(path)
+        {
+            err_handler(e);
+        }
     }
 }
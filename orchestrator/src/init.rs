@@ -0,0 +1,195 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use log::debug;
+use walkdir::WalkDir;
+
+use crate::{
+    command_line::{
+        Configuration, DEFAULT_CONFIG_FILE_NAME, InitOptions, default_config_values, get_dotted,
+    },
+    file_formatter::formattable_file_path,
+};
+
+/// The maximum number of source files read when inferring option values.
+const MAX_SAMPLES: usize = 100;
+
+/// An existing source file, used to infer option values.
+pub struct SourceSample {
+    pub path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+fn sample_sources(dir: &Path) -> Vec<SourceSample> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file() && formattable_file_path(entry.path()))
+        .filter_map(|entry| match fs::read(entry.path()) {
+            Ok(contents) => Some(SourceSample {
+                path: entry.into_path(),
+                contents,
+            }),
+            Err(e) => {
+                debug!("Skipping {} when sampling: {e}", entry.path().display());
+                None
+            }
+        })
+        .take(MAX_SAMPLES)
+        .collect()
+}
+
+fn config_file_contents<C: Configuration>(inferred: &toml::Table) -> anyhow::Result<String> {
+    let defaults = default_config_values::<C>()?;
+
+    // Options in tables are written after all top-level options, with each
+    // table's options grouped under its header.
+    let mut items: Vec<_> = C::docs().into_iter().collect();
+    items.sort_by_key(|item| item.name.rsplit_once('.').map(|(table, _)| table));
+
+    let mut out = String::new();
+    let mut current_table = None;
+    writeln!(out, "# Uncomment an option to change it from its default.")?;
+    for item in items {
+        let (table, name) = match item.name.rsplit_once('.') {
+            Some((table, name)) => (Some(table), name),
+            None => (None, item.name),
+        };
+        if table != current_table {
+            if let Some(table) = table {
+                writeln!(out, "\n[{table}]")?;
+            }
+            current_table = table;
+        }
+
+        writeln!(out)?;
+        for line in item.description.lines() {
+            writeln!(out, "{}", format!("# {line}").trim_end())?;
+        }
+        writeln!(out, "# {}", item.kind)?;
+        match (
+            get_dotted(inferred, item.name),
+            get_dotted(&defaults, item.name),
+        ) {
+            (Some(value), _) => writeln!(out, "{name} = {value} # inferred")?,
+            (None, Some(value)) => writeln!(out, "# {name} = {value}")?,
+            (None, None) => writeln!(out, "# {name} = {}", item.default)?,
+        }
+    }
+
+    Ok(out)
+}
+
+/// Writes a `pasfmt.toml` documenting every option of `C` to the directory
+/// in `options`, returning the path of the written file.
+pub fn init_config_file<C: Configuration>(options: &InitOptions) -> anyhow::Result<PathBuf> {
+    let path = options.dir.join(DEFAULT_CONFIG_FILE_NAME);
+    if path.exists() && !options.force {
+        anyhow::bail!(
+            "{} already exists, use `--force` to overwrite it",
+            path.display()
+        );
+    }
+
+    let inferred = match options.infer {
+        true => C::infer(&sample_sources(&options.dir)),
+        false => toml::Table::new(),
+    };
+    let contents = config_file_contents::<C>(&inferred)?;
+    fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_line::{ConfigItem, ConfigKind};
+    use indoc::indoc;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(default)]
+    struct Settings {
+        width: u32,
+        name: String,
+        nested: Nested,
+    }
+
+    #[derive(Serialize, Deserialize, Default)]
+    #[serde(default)]
+    struct Nested {
+        enabled: bool,
+    }
+
+    impl Default for Settings {
+        fn default() -> Self {
+            Self {
+                width: 80,
+                name: "x".to_owned(),
+                nested: Nested::default(),
+            }
+        }
+    }
+
+    impl Configuration for Settings {
+        fn docs() -> impl IntoIterator<Item = ConfigItem> {
+            [
+                ConfigItem {
+                    name: "nested.enabled",
+                    description: "Whether it is enabled",
+                    kind: ConfigKind::Boolean,
+                    default: "false".to_owned(),
+                },
+                ConfigItem {
+                    name: "width",
+                    description: "The width\n\nof things",
+                    kind: ConfigKind::unsigned(u32::MAX),
+                    default: "80".to_owned(),
+                },
+                ConfigItem {
+                    name: "name",
+                    description: "The name",
+                    kind: ConfigKind::String,
+                    default: "x".to_owned(),
+                },
+            ]
+        }
+    }
+
+    #[test]
+    fn contents_document_each_option_with_tables_last() -> anyhow::Result<()> {
+        let mut inferred = toml::Table::new();
+        inferred.insert("name".to_owned(), "y".into());
+        inferred.insert("unknown".to_owned(), 1.into());
+
+        assert_eq!(
+            config_file_contents::<Settings>(&inferred)?,
+            indoc! {r#"
+                # Uncomment an option to change it from its default.
+
+                # The width
+                #
+                # of things
+                # <unsigned integer>
+                # width = 80
+
+                # The name
+                # <string>
+                name = "y" # inferred
+
+                [nested]
+
+                # Whether it is enabled
+                # <boolean>
+                # enabled = false
+            "#}
+        );
+
+        Ok(())
+    }
+}
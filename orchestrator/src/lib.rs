@@ -2,8 +2,11 @@
 #![deny(clippy::enum_glob_use)]
 
 pub mod command_line;
+mod dump;
 pub mod file_formatter;
 pub mod formatting_orchestrator;
+pub mod init;
+mod profile;
 
 pub trait ErrHandler: Fn(anyhow::Error) + Sync {}
 impl<T: Fn(anyhow::Error) + Sync> ErrHandler for T {}
@@ -13,4 +16,5 @@ pub mod predule {
     pub use crate::command_line::{pasfmt_config, *};
     pub use crate::file_formatter::*;
     pub use crate::formatting_orchestrator::*;
+    pub use crate::init::*;
 }
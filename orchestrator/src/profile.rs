@@ -0,0 +1,112 @@
+use std::{fs::File, io::BufWriter, path::Path, sync::Mutex, time::Instant};
+
+use anyhow::Context;
+use pasfmt_core::prelude::StageTiming;
+use serde_json::{Value, json};
+
+/// Collects the time spent formatting each file, and each stage within, as
+/// Chrome trace events.
+///
+/// The resulting trace can be viewed in `chrome://tracing` or
+/// <https://ui.perfetto.dev>.
+pub(crate) struct Profiler {
+    origin: Instant,
+    events: Mutex<Vec<Value>>,
+}
+
+impl Profiler {
+    pub(crate) fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            events: Mutex::new(vec![]),
+        }
+    }
+
+    fn complete_event(
+        &self,
+        name: &str,
+        category: &str,
+        start: Instant,
+        duration: std::time::Duration,
+        file: &str,
+    ) -> Value {
+        json!({
+            "name": name,
+            "cat": category,
+            "ph": "X",
+            "ts": start.duration_since(self.origin).as_secs_f64() * 1e6,
+            "dur": duration.as_secs_f64() * 1e6,
+            "pid": 0,
+            // Files are formatted in parallel; each worker thread is a track.
+            "tid": rayon::current_thread_index().unwrap_or_default(),
+            "args": { "file": file },
+        })
+    }
+
+    /// Records the formatting of a single file, with its stages nested within.
+    pub(crate) fn record(&self, file: &str, start: Instant, stage_timings: &[StageTiming]) {
+        let file_event = self.complete_event(file, "file", start, start.elapsed(), file);
+        let stage_events = stage_timings.iter().map(|timing| {
+            self.complete_event(timing.stage, "stage", timing.start, timing.duration, file)
+        });
+
+        let mut events = self.events.lock().unwrap();
+        events.push(file_event);
+        events.extend(stage_events);
+    }
+
+    pub(crate) fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create profile '{}'", path.display()))?;
+        let events = self.events.lock().unwrap();
+        serde_json::to_writer(
+            BufWriter::new(file),
+            &json!({
+                "traceEvents": *events,
+                "displayTimeUnit": "ms",
+            }),
+        )
+        .with_context(|| format!("failed to write profile '{}'", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn file_and_stage_events() {
+        let profiler = Profiler::new();
+        let start = profiler.origin + Duration::from_micros(10);
+        profiler.record(
+            "a.pas",
+            start,
+            &[StageTiming {
+                stage: "lex",
+                start: start + Duration::from_micros(5),
+                duration: Duration::from_micros(20),
+            }],
+        );
+
+        let events = profiler.events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["name"], "a.pas");
+        assert_eq!(events[0]["cat"], "file");
+        assert_eq!(events[0]["ts"], 10.0);
+        assert_eq!(
+            events[1],
+            json!({
+                "name": "lex",
+                "cat": "stage",
+                "ph": "X",
+                "ts": 15.0,
+                "dur": 20.0,
+                "pid": 0,
+                "tid": 0,
+                "args": { "file": "a.pas" },
+            })
+        );
+    }
+}
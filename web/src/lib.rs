@@ -1,5 +1,5 @@
 use pasfmt::{FormattingConfig, make_formatter};
-use pasfmt_core::prelude::FileOptions;
+use pasfmt_core::{formatter::Formatter, prelude::FileOptions};
 use wasm_bindgen::prelude::*;
 
 // Everything here needs to be kept backwards-compatible, because the web demo
@@ -8,6 +8,7 @@ use wasm_bindgen::prelude::*;
 #[wasm_bindgen]
 pub struct SettingsWrapper {
     config: FormattingConfig,
+    formatter: Formatter,
 }
 
 #[wasm_bindgen]
@@ -15,7 +16,10 @@ impl SettingsWrapper {
     #[wasm_bindgen(constructor)]
     pub fn new(settings: String) -> Result<Self, String> {
         let config = toml::from_str(&settings).map_err(|e| e.to_string())?;
-        Ok(SettingsWrapper { config })
+        // Settings which cannot make a formatter are reported here, rather than
+        // in the output of `fmt`.
+        let formatter = make_formatter(&config).map_err(|e| format!("{e:#}"))?;
+        Ok(SettingsWrapper { config, formatter })
     }
 
     #[wasm_bindgen]
@@ -31,5 +35,5 @@ pub fn default_settings_toml() -> String {
 
 #[wasm_bindgen]
 pub fn fmt(src: &str, settings: &SettingsWrapper) -> String {
-    make_formatter(&settings.config).format(src, FileOptions::new())
+    settings.formatter.format(src, FileOptions::new())
 }